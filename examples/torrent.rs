@@ -1,43 +1,55 @@
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 struct Torrent<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
     announce: Option<String>,
-    #[serde(rename = "announce-list")]
+    #[serde(rename = "announce-list", skip_serializing_if = "Option::is_none")]
     announce_list: Option<Vec<Vec<String>>>,
     #[serde(borrow)]
     info: TorrentInfo<'a>,
-    #[serde(rename = "creation date")]
+    #[serde(rename = "creation date", skip_serializing_if = "Option::is_none")]
     creation_date: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     comment: Option<String>,
 
-    #[serde(rename = "created by")]
+    #[serde(rename = "created by", skip_serializing_if = "Option::is_none")]
     created_by: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 struct TorrentInfo<'a> {
     #[serde(rename = "piece length")]
     piece_length: usize,
-    #[serde(borrow)]
+    #[serde(borrow, with = "serde_bytes")]
     pieces: &'a [u8],
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_zero")]
     private: u8,
     name: String,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    length: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     files: Option<Vec<TorrentFile>>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 struct TorrentFile {
     length: usize,
     path: Vec<String>,
 }
 
+fn is_zero(v: &u8) -> bool {
+    *v == 0
+}
+
 fn main() {
     let content = std::fs::read("./examples/ubuntu.torrent").unwrap();
 
     let torrent: Torrent = serde_bencode::from_binary(&content).unwrap();
 
     println!("Torrent info: {torrent:?}");
+
+    let encoded = serde_bencode::to_binary(&torrent).unwrap();
+    println!("Re-encoded identically: {}", encoded == content);
 }
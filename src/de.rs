@@ -1,80 +1,494 @@
-use crate::stream::BinaryStream;
-use crate::types;
-use crate::{Error, Result};
-use std::str::from_utf8;
+use crate::number;
+use crate::stream::{BinaryStream, Input, ReaderStream, Reference};
+use crate::{
+    BoolEncoding, Error, ErrorKind, FloatEncoding, Limit, Limits, OptionEncoding, Result,
+    StringDecoding, Warning, WarningKind,
+};
+use std::any::type_name;
+use std::cmp::Ordering;
+use std::io;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::str::{from_utf8, FromStr};
 
+use serde::de::value::{BorrowedStrDeserializer, SeqDeserializer, StrDeserializer};
+use serde::de::DeserializeOwned;
+use serde::forward_to_deserialize_any;
 use serde::{de, Deserialize};
 
-pub struct Deserializer<'de> {
-    input: BinaryStream<'de>,
+#[derive(Debug, Clone, Copy, Default)]
+struct Options {
+    option_encoding: OptionEncoding,
+    bool_encoding: BoolEncoding,
+    float_encoding: FloatEncoding,
+    string_decoding: StringDecoding,
+    canonical: bool,
+    lenient: bool,
+    limits: Limits,
+}
+
+/// Reads from a borrowed slice by default, see [`Deserializer::from_reader`]
+/// for reading from anything else.
+pub struct Deserializer<'de, I = BinaryStream<'de>> {
+    input: I,
+    // Byte strings and integers read from input that can't be borrowed.
+    scratch: Vec<u8>,
+    options: Options,
+    depth: usize,
+    total_bytes: usize,
+    warnings: Vec<Warning>,
+    // Offset of the value being read as a list of itself in lenient mode,
+    // which can't be repaired again.
+    repairing: Option<usize>,
+    lifetime: PhantomData<&'de [u8]>,
 }
 
 pub fn from_binary<'a, T: Deserialize<'a>>(data: &'a [u8]) -> Result<T> {
     let mut deserializer = Deserializer::new(data);
+    let value = T::deserialize(&mut deserializer).map_err(|e| e.or_offset(0))?;
+    deserializer.end()?;
+
+    Ok(value)
+}
+
+/// Deserializes a value from `reader`, reading it until its end. Nothing is
+/// borrowed from the input, so `T` can't hold references into it.
+pub fn from_reader<R: io::Read, T: DeserializeOwned>(reader: R) -> Result<T> {
+    let mut deserializer = Deserializer::from_reader(reader);
+    let value = T::deserialize(&mut deserializer).map_err(|e| e.or_offset(0))?;
+    deserializer.end()?;
+
+    Ok(value)
+}
+
+/// Deserializes a value from the front of `data` and returns it together with
+/// the bytes that follow it, e.g. the piece data after a BEP 9 message.
+pub fn from_binary_prefix<'a, T: Deserialize<'a>>(data: &'a [u8]) -> Result<(T, &'a [u8])> {
+    let mut deserializer = Deserializer::new(data);
+    let value = T::deserialize(&mut deserializer).map_err(|e| e.or_offset(0))?;
+
+    Ok((value, deserializer.remainder()))
+}
+
+/// Like [`from_binary`], in [lenient](Deserializer::lenient) mode. Also
+/// returns what had to be repaired.
+pub fn from_binary_lenient<'a, T: Deserialize<'a>>(data: &'a [u8]) -> Result<(T, Vec<Warning>)> {
+    let mut deserializer = Deserializer::new(data).lenient(true);
+    let value = T::deserialize(&mut deserializer).map_err(|e| e.or_offset(0))?;
+    deserializer.end()?;
+
+    Ok((value, deserializer.warnings))
+}
+
+/// Whether `data` is a single value in canonical form, i.e. the only encoding
+/// that re-serializing it would produce. Fails if it is nested deeper than
+/// the default [`Limits`] allow, since the rest can't be checked.
+pub fn is_canonical(data: &[u8]) -> Result<bool> {
+    let mut deserializer = Deserializer::new(data).canonical(true);
 
-    // TODO: check trailing characters
-    T::deserialize(&mut deserializer)
+    match de::IgnoredAny::deserialize(&mut deserializer).and_then(|_| deserializer.end()) {
+        Ok(()) => Ok(true),
+        Err(err) if matches!(err.kind(), ErrorKind::LimitExceeded(_)) => Err(err),
+        Err(_) => Ok(false),
+    }
 }
 
 impl<'de> Deserializer<'de> {
     pub fn new(data: &'de [u8]) -> Self {
+        Self::with_input(BinaryStream::new(data))
+    }
+
+    /// Input that has not been consumed yet.
+    pub fn remainder(&self) -> &'de [u8] {
+        self.input.remainder()
+    }
+}
+
+impl<'de, R: io::Read> Deserializer<'de, ReaderStream<R>> {
+    /// Reads from `reader`, which is buffered internally. Byte strings are
+    /// read in chunks, a length prefix alone does not allocate anything.
+    pub fn from_reader(reader: R) -> Self {
+        Self::with_input(ReaderStream::new(reader))
+    }
+}
+
+impl<'de, I: Input<'de>> Deserializer<'de, I> {
+    fn with_input(input: I) -> Self {
         Self {
-            input: BinaryStream::new(data),
+            input,
+            scratch: Vec::new(),
+            options: Options::default(),
+            depth: 0,
+            total_bytes: 0,
+            warnings: Vec::new(),
+            repairing: None,
+            lifetime: PhantomData,
+        }
+    }
+
+    /// Sets how options are expected to be encoded, see [`OptionEncoding`].
+    pub fn option_encoding(mut self, encoding: OptionEncoding) -> Self {
+        self.options.option_encoding = encoding;
+        self
+    }
+
+    /// Sets how booleans are expected to be encoded, see [`BoolEncoding`].
+    pub fn bool_encoding(mut self, encoding: BoolEncoding) -> Self {
+        self.options.bool_encoding = encoding;
+        self
+    }
+
+    /// Sets how floats are expected to be encoded, see [`FloatEncoding`].
+    pub fn float_encoding(mut self, encoding: FloatEncoding) -> Self {
+        self.options.float_encoding = encoding;
+        self
+    }
+
+    /// Sets how byte strings that are not valid UTF-8 are read as strings,
+    /// see [`StringDecoding`].
+    pub fn string_decoding(mut self, decoding: StringDecoding) -> Self {
+        self.options.string_decoding = decoding;
+        self
+    }
+
+    /// Rejects dictionaries whose keys are not sorted by their raw bytes or
+    /// are repeated. Integers and lengths are always required to be minimal.
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.options.canonical = canonical;
+        self
+    }
+
+    /// Accepts the following mistakes found in real-world torrents, and
+    /// records a [`Warning`] for each of them:
+    ///
+    /// - dictionary keys out of order or repeated (unless in canonical mode),
+    /// - integers written as byte strings, like `3:123`, where an integer is
+    ///   expected,
+    /// - a single value where a list is expected, read as a one-element list,
+    /// - ASCII whitespace after the value, see [`Deserializer::end`].
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.options.lenient = lenient;
+        self
+    }
+
+    /// Repairs made so far in lenient mode.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Sets the limits to enforce, see [`Limits`].
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.options.limits = limits;
+        self
+    }
+
+    /// Fails unless the whole input has been consumed. In lenient mode,
+    /// trailing ASCII whitespace is consumed instead.
+    pub fn end(&mut self) -> Result<()> {
+        if !self.options.lenient {
+            return match self.input.peek()? {
+                None => Ok(()),
+                Some(_) => Err(ErrorKind::TrailingData.at(self.input.position())),
+            };
+        }
+
+        let start = self.input.position();
+        while let Some(b) = self.input.peek()? {
+            if !b.is_ascii_whitespace() {
+                return Err(ErrorKind::TrailingData.at(start));
+            }
+            self.input.discard();
+        }
+        if self.input.position() > start {
+            self.warn(start, WarningKind::TrailingWhitespace);
+        }
+
+        Ok(())
+    }
+
+    fn warn(&mut self, offset: usize, kind: WarningKind) {
+        self.warnings.push(Warning { offset, kind });
+    }
+
+    /// Turns this into an iterator over values written back to back, each of
+    /// type `T`.
+    pub fn into_stream<T: Deserialize<'de>>(self) -> StreamDeserializer<'de, T, I> {
+        StreamDeserializer {
+            offset: self.input.position(),
+            de: self,
+            failed: false,
+            output: PhantomData,
+        }
+    }
+
+    /// Steps into the list at the current position, usually the top-level
+    /// one, to read its elements one at a time instead of all at once.
+    pub fn iter_list<T: Deserialize<'de>>(&mut self) -> Result<ListIter<'_, 'de, T, I>> {
+        self.expect(b'l', "list")?;
+
+        Ok(ListIter {
+            collection: self.step_in()?,
+            done: false,
+            output: PhantomData,
+        })
+    }
+
+    /// Steps into the dictionary at the current position, usually the
+    /// top-level one, to read its entries one at a time instead of all at
+    /// once.
+    pub fn iter_dict<K, V>(&mut self) -> Result<DictIter<'_, 'de, K, V, I>>
+    where
+        K: Deserialize<'de>,
+        V: Deserialize<'de>,
+    {
+        self.expect(b'd', "dictionary")?;
+
+        Ok(DictIter {
+            collection: self.step_in()?,
+            done: false,
+            output: PhantomData,
+        })
+    }
+
+    /// Like [`Deserializer::nested`], for a collection read by an iterator.
+    /// The level is left when the iterator is dropped.
+    fn step_in(&mut self) -> Result<BencodeCollection<'_, 'de, I>> {
+        if self.depth >= self.options.limits.max_depth {
+            return Err(ErrorKind::LimitExceeded(Limit::Depth).at(self.input.position()));
+        }
+        self.depth += 1;
+
+        Ok(BencodeCollection::new(self))
+    }
+
+    /// Consumes `byte`, which starts a value of the `expected` type.
+    fn expect(&mut self, byte: u8, expected: &str) -> Result<()> {
+        if self.input.try_peek()? != byte {
+            return Err(self.invalid_type(expected));
+        }
+
+        self.input.try_next()?;
+        Ok(())
+    }
+
+    fn expect_end(&mut self) -> Result<()> {
+        if self.input.try_peek()? != b'e' {
+            return Err(ErrorKind::ExpectedEnd.at(self.input.position()));
+        }
+
+        self.input.try_next()?;
+        Ok(())
+    }
+
+    /// Error for the value at the current position not being of the
+    /// `expected` type.
+    fn invalid_type(&mut self, expected: &str) -> Error {
+        let rest = match self.input.lookahead() {
+            Ok(rest) => rest,
+            Err(err) => return err,
+        };
+        let found = match rest.first() {
+            Some(b'i') => "integer".to_string(),
+            Some(b'l') => "list".to_string(),
+            Some(b'd') => "dictionary".to_string(),
+            Some(b'0'..=b'9') => {
+                let len = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+                match rest.get(len) {
+                    Some(b':') => {
+                        format!("byte string of length {}", from_utf8(&rest[..len]).unwrap())
+                    }
+                    // The length goes on past what has been read so far.
+                    _ => "byte string".to_string(),
+                }
+            }
+            Some(&b) => format!("unexpected byte {:?}", b as char),
+            None => "end of input".to_string(),
+        };
+
+        ErrorKind::InvalidType {
+            expected: expected.to_string(),
+            found,
         }
+        .at(self.input.position())
     }
 
-    fn parse_seq_number(&mut self, terminator: u8) -> Result<types::Number> {
-        fn to_num(n: u8) -> Option<i64> {
-            Some((n as char).to_digit(10)? as i64)
+    /// Digits, with an optional leading `-`, of an integer ended by `terminator`.
+    /// They are kept as text so that each target type can parse its own range.
+    ///
+    /// Follows BEP 3: no leading zeros except for `0` itself, and no `-0`.
+    fn parse_seq_number(&mut self, terminator: u8) -> Result<&str> {
+        let start = self.input.position();
+        self.scratch.clear();
+
+        if self.input.peek()? == Some(b'-') {
+            self.input.discard();
+            self.scratch.push(b'-');
         }
+        let sign = self.scratch.len();
 
-        let mut init = 0i64;
-        let next = self.input.try_next()?;
-        if next != b'-' {
-            init = to_num(next).ok_or(Error::Syntax)?;
+        loop {
+            match self.input.peek()? {
+                Some(b) if b.is_ascii_digit() => {
+                    if self.scratch.len() - sign >= self.options.limits.max_integer_len {
+                        return Err(ErrorKind::LimitExceeded(Limit::IntegerLen).at(start));
+                    }
+                    self.input.discard();
+                    self.scratch.push(b);
+                }
+                None => return Err(ErrorKind::Eof { needed: 1 }.at(self.input.position())),
+                Some(_) if self.scratch.len() == sign => {
+                    return Err(ErrorKind::Syntax.at(self.input.position()))
+                }
+                Some(b) if b != terminator => {
+                    return Err(ErrorKind::MissingTerminator.at(self.input.position()))
+                }
+                Some(_) => break,
+            }
         }
 
-        // TODO: if sequence just end(eof), error not be,
-        // which may cause logical bug
-        let mut result = self
-            .input
-            .take_while(|&v| v != terminator)
-            .try_fold(init, |acc, v| {
-                let v = to_num(v).ok_or(Error::Syntax)?;
+        let token = from_utf8(&self.scratch).map_err(|_| ErrorKind::Syntax.at(start))?;
+        number::check_integer(token).map_err(|e| e.or_offset(start))?;
+
+        self.input.discard();
+
+        Ok(token)
+    }
+
+    pub(crate) fn parse_numeric<T: FromStr>(&mut self) -> Result<T> {
+        let start = self.input.position();
+        if self.options.lenient && self.input.try_peek()?.is_ascii_digit() {
+            // Only the quotes are forgiven, the digits follow the same rules
+            // as those of an integer.
+            let str = self.parse_str()?;
+            number::check_integer(str).map_err(|e| e.or_offset(start))?;
 
-                Ok(acc * 10 + v)
+            let number = str.parse().map_err(|_| {
+                ErrorKind::IntegerOverflow {
+                    number: str.to_string(),
+                    target: type_name::<T>(),
+                }
+                .at(start)
             })?;
+            self.warn(start, WarningKind::IntegerAsString);
 
-        if init == 0 {
-            result *= -1;
+            return Ok(number);
         }
 
-        Ok(result)
+        self.expect(b'i', "integer")?;
+
+        let number = self.parse_seq_number(b'e')?;
+        number.parse().map_err(|_| {
+            ErrorKind::IntegerOverflow {
+                number: number.to_string(),
+                target: type_name::<T>(),
+            }
+            .at(start)
+        })
     }
 
-    pub(crate) fn parse_numeric<T: TryFrom<i64>>(&mut self) -> Result<T> {
-        if self.input.try_next()? != b'i' {
-            return Err(Error::ExpectedNumber);
+    pub(crate) fn parse_bytes(&mut self) -> Result<Reference<'de, '_>> {
+        let start = self.input.position();
+        match self.input.try_peek()? {
+            b'-' => return Err(ErrorKind::NegativeLength.at(start)),
+            b'0'..=b'9' => {}
+            _ => return Err(self.invalid_type("byte string")),
         }
 
-        self.parse_seq_number(b'e')?
-            .try_into()
-            .map_err(|_| Error::ExpectedNumber)
+        let len = self.parse_seq_number(b':')?;
+        let len = len.parse().map_err(|_| {
+            ErrorKind::IntegerOverflow {
+                number: len.to_string(),
+                target: "a byte string length",
+            }
+            .at(start)
+        })?;
+
+        let limits = &self.options.limits;
+        if len > limits.max_bytes_len {
+            return Err(ErrorKind::LimitExceeded(Limit::BytesLen).at(start));
+        }
+        self.total_bytes = self.total_bytes.saturating_add(len);
+        if self.total_bytes > limits.max_total_bytes {
+            return Err(ErrorKind::LimitExceeded(Limit::TotalBytes).at(start));
+        }
+
+        self.input.take(len, &mut self.scratch)
+    }
+
+    /// Moves past a value without visiting or copying any of it. The value is
+    /// still checked like any other, limits and canonical order included.
+    fn skip_value(&mut self) -> Result<()> {
+        match self.input.try_peek()? {
+            b'0'..=b'9' | b'-' => self.parse_bytes().map(drop),
+            b'i' => {
+                self.input.try_next()?;
+                self.parse_seq_number(b'e').map(drop)
+            }
+            b'l' => {
+                self.input.try_next()?;
+                self.nested(|de| BencodeCollection::new(de).skip_elements())?;
+                self.expect_end()
+            }
+            b'd' => {
+                self.input.try_next()?;
+                self.nested(|de| BencodeCollection::new(de).skip_entries())?;
+                self.expect_end()
+            }
+            _ => Err(ErrorKind::Syntax.at(self.input.position())),
+        }
     }
 
-    pub(crate) fn parse_bytes(&mut self) -> Result<&'de [u8]> {
-        let len = self.parse_seq_number(b':')? as usize;
+    /// Runs `f` one nesting level deeper.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= self.options.limits.max_depth {
+            return Err(ErrorKind::LimitExceeded(Limit::Depth).at(self.input.position()));
+        }
+
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+
+        result
+    }
 
-        self.input.try_take(len)
+    pub(crate) fn parse_str(&mut self) -> Result<&str> {
+        let bytes = self.parse_bytes()?.into_slice();
+        std::str::from_utf8(bytes).map_err(|_| ErrorKind::InvalidString.into())
     }
 
-    pub(crate) fn parse_str(&mut self) -> Result<&'de str> {
-        let bytes = self.parse_bytes()?;
-        std::str::from_utf8(bytes).map_err(|_| Error::InvalidString)
+    /// Both widths are accepted whatever the target is, serde converts them.
+    fn deserialize_float<V>(&mut self, visitor: V, single: bool) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.options.float_encoding {
+            FloatEncoding::Unsupported => Err(ErrorKind::TypeNotSupported.into()),
+            FloatEncoding::Decimal => {
+                let str = self.parse_str()?;
+                let invalid = |_| Error::from(ErrorKind::InvalidFloat);
+
+                if single {
+                    visitor.visit_f32(str.parse().map_err(invalid)?)
+                } else {
+                    visitor.visit_f64(str.parse().map_err(invalid)?)
+                }
+            }
+            FloatEncoding::Binary => {
+                let bytes = &*self.parse_bytes()?;
+
+                if let Ok(bytes) = bytes.try_into() {
+                    visitor.visit_f32(f32::from_be_bytes(bytes))
+                } else if let Ok(bytes) = bytes.try_into() {
+                    visitor.visit_f64(f64::from_be_bytes(bytes))
+                } else {
+                    Err(ErrorKind::InvalidFloat.into())
+                }
+            }
+        }
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de, I: Input<'de>> de::Deserializer<'de> for &mut Deserializer<'de, I> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
@@ -82,26 +496,48 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         V: de::Visitor<'de>,
     {
         match self.input.try_peek()? {
-            b'0'..=b'9' => {
+            b'0'..=b'9' | b'-' => {
                 let bytes = self.parse_bytes()?;
 
-                match from_utf8(bytes) {
-                    Ok(str) => visitor.visit_string(str.to_owned()),
-                    Err(_) => visitor.visit_byte_buf(bytes.to_owned()),
+                visit_any_bytes(bytes, visitor)
+            }
+            b'i' => {
+                self.input.try_next()?;
+                let number = self.parse_seq_number(b'e')?;
+
+                if let Ok(n) = number.parse() {
+                    visitor.visit_i64(n)
+                } else if let Ok(n) = number.parse() {
+                    visitor.visit_u64(n)
+                } else if let Ok(n) = number.parse() {
+                    visitor.visit_i128(n)
+                } else if let Ok(n) = number.parse() {
+                    visitor.visit_u128(n)
+                } else {
+                    visitor.visit_map(BigNumber {
+                        digits: number,
+                        key_visited: false,
+                    })
                 }
             }
-            b'i' => self.deserialize_i64(visitor),
             b'l' => self.deserialize_seq(visitor),
             b'd' => self.deserialize_map(visitor),
-            _ => Err(Error::Syntax),
+            _ => Err(ErrorKind::Syntax.at(self.input.position())),
         }
     }
 
-    fn deserialize_bool<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_bool<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        Err(Error::TypeNotSupported)
+        match self.options.bool_encoding {
+            BoolEncoding::Unsupported => Err(ErrorKind::TypeNotSupported.into()),
+            BoolEncoding::Integer => match self.parse_numeric()? {
+                0 => visitor.visit_bool(false),
+                1 => visitor.visit_bool(true),
+                n => Err(ErrorKind::InvalidBool(n).into()),
+            },
+        }
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
@@ -132,6 +568,13 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_i64(self.parse_numeric()?)
     }
 
+    fn deserialize_i128<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_i128(self.parse_numeric()?)
+    }
+
     fn deserialize_u8<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
@@ -160,32 +603,50 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_u64(self.parse_numeric()?)
     }
 
-    fn deserialize_f32<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_u128<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        Err(Error::TypeNotSupported)
+        visitor.visit_u128(self.parse_numeric()?)
     }
 
-    fn deserialize_f64<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_f32<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        Err(Error::TypeNotSupported)
+        self.deserialize_float(visitor, true)
     }
 
-    fn deserialize_char<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_f64<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        Err(Error::TypeNotSupported)
+        self.deserialize_float(visitor, false)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let str = self.parse_str()?;
+        let mut chars = str.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(de::Error::invalid_value(
+                de::Unexpected::Str(str),
+                &"a single character",
+            )),
+        }
     }
 
     fn deserialize_str<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_borrowed_str(self.parse_str()?)
+        let decoding = self.options.string_decoding;
+
+        visit_text(self.parse_bytes()?, decoding, visitor)
     }
 
     fn deserialize_string<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
@@ -199,7 +660,10 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_borrowed_bytes(self.parse_bytes()?)
+        match self.parse_bytes()? {
+            Reference::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Reference::Copied(bytes) => visitor.visit_bytes(bytes),
+        }
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
@@ -213,25 +677,44 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_some(self)
+        // A missing struct field is turned into `None` by serde itself.
+        match self.options.option_encoding {
+            OptionEncoding::Bare => visitor.visit_some(self),
+            OptionEncoding::List => {
+                self.expect(b'l', "list")?;
+
+                if self.input.try_peek()? == b'e' {
+                    self.input.try_next()?;
+                    return visitor.visit_none();
+                }
+
+                let value = self.nested(|de| visitor.visit_some(de))?;
+                self.expect_end()?;
+
+                Ok(value)
+            }
+        }
     }
 
-    fn deserialize_unit<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_unit<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        self.expect(b'l', "empty list")?;
+        self.expect_end()?;
+
+        visitor.visit_unit()
     }
 
     fn deserialize_unit_struct<V>(
         self,
         _name: &'static str,
-        _visitor: V,
+        visitor: V,
     ) -> std::result::Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        self.deserialize_unit(visitor)
     }
 
     fn deserialize_newtype_struct<V>(
@@ -249,17 +732,25 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        if self.input.try_next()? == b'l' {
-            let value = visitor.visit_seq(BencodeCollection { de: self })?;
+        let start = self.input.position();
+        if self.options.lenient
+            && self.repairing != Some(start)
+            && matches!(self.input.try_peek()?, b'0'..=b'9' | b'i' | b'd')
+        {
+            self.warn(start, WarningKind::ValueAsList);
 
-            if self.input.try_next()? == b'e' {
-                Ok(value)
-            } else {
-                Err(Error::ExpectedEnd)
-            }
-        } else {
-            Err(Error::ExpectedList)
+            let outer = self.repairing.replace(start);
+            let value = self.nested(|de| visitor.visit_seq(Singleton { de, done: false }));
+            self.repairing = outer;
+
+            return value;
         }
+
+        self.expect(b'l', "list")?;
+        let value = self.nested(|de| visitor.visit_seq(BencodeCollection::new(de)))?;
+        self.expect_end()?;
+
+        Ok(value)
     }
 
     fn deserialize_tuple<V>(
@@ -289,17 +780,11 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        if self.input.try_next()? == b'd' {
-            let value = visitor.visit_map(BencodeCollection { de: self })?;
+        self.expect(b'd', "dictionary")?;
+        let value = self.nested(|de| visitor.visit_map(BencodeCollection::new(de)))?;
+        self.expect_end()?;
 
-            if self.input.try_next()? == b'e' {
-                Ok(value)
-            } else {
-                Err(Error::ExpectedEnd)
-            }
-        } else {
-            Err(Error::ExpectedDictionary)
-        }
+        Ok(value)
     }
 
     fn deserialize_struct<V>(
@@ -316,14 +801,32 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     fn deserialize_enum<V>(
         self,
-        _name: &'static str,
-        _variants: &'static [&'static str],
-        _visitor: V,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
     ) -> std::result::Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        match self.input.try_peek()? {
+            b'0'..=b'9' => {
+                let decoding = self.options.string_decoding;
+                MapKey(self.parse_bytes()?, decoding).deserialize_enum(name, variants, visitor)
+            }
+            b'd' => {
+                self.input.try_next()?;
+                let value = self.nested(|de| {
+                    visitor.visit_enum(BencodeEnum {
+                        de,
+                        variant: Vec::new(),
+                    })
+                })?;
+                self.expect_end()?;
+
+                Ok(value)
+            }
+            _ => Err(self.invalid_type("byte string or dictionary")),
+        }
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
@@ -337,15 +840,293 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_any(visitor)
+        self.skip_value()?;
+        visitor.visit_unit()
+    }
+
+    /// Must match `ser::Serializer::is_human_readable`.
+    fn is_human_readable(&self) -> bool {
+        false
     }
 }
 
-struct BencodeCollection<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+/// Iterator over values written back to back, such as a journal or a capture
+/// of KRPC messages, see [`Deserializer::into_stream`].
+///
+/// It stops after the first error. A value cut short by the end of the input
+/// fails with [`ErrorKind::Eof`]. The [`Limits`] apply to each value on its
+/// own, as with [`PushParser`](crate::PushParser).
+pub struct StreamDeserializer<'de, T, I = BinaryStream<'de>> {
+    de: Deserializer<'de, I>,
+    offset: usize,
+    failed: bool,
+    output: PhantomData<T>,
 }
 
-impl<'a, 'de> de::SeqAccess<'de> for BencodeCollection<'a, 'de> {
+impl<'de, T> StreamDeserializer<'de, T> {
+    pub fn new(data: &'de [u8]) -> Self {
+        Self {
+            de: Deserializer::new(data),
+            offset: 0,
+            failed: false,
+            output: PhantomData,
+        }
+    }
+}
+
+impl<'de, T, I> StreamDeserializer<'de, T, I> {
+    /// Offset just past the last value read successfully, which is where
+    /// reading has to resume once more input is available.
+    pub fn byte_offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'de, T: Deserialize<'de>, I: Input<'de>> Iterator for StreamDeserializer<'de, T, I> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        match self.de.input.peek() {
+            Ok(Some(_)) => {}
+            Ok(None) => return None,
+            Err(err) => {
+                self.failed = true;
+                return Some(Err(err));
+            }
+        }
+
+        self.de.total_bytes = 0;
+        match T::deserialize(&mut self.de) {
+            Ok(value) => {
+                self.offset = self.de.input.position();
+                Some(Ok(value))
+            }
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err.or_offset(self.offset)))
+            }
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>, I: Input<'de>> FusedIterator for StreamDeserializer<'de, T, I> {}
+
+/// Iterator over the elements of a list, see [`Deserializer::iter_list`].
+///
+/// It stops after the first error. Once it is over, the deserializer is
+/// past the end of the list.
+pub struct ListIter<'a, 'de, T, I = BinaryStream<'de>> {
+    collection: BencodeCollection<'a, 'de, I>,
+    done: bool,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<'de, T: Deserialize<'de>, I: Input<'de>> Iterator for ListIter<'_, 'de, T, I> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = match de::SeqAccess::next_element(&mut self.collection) {
+            Ok(Some(element)) => return Some(Ok(element)),
+            Ok(None) => self.collection.de.expect_end(),
+            Err(err) => Err(err),
+        };
+        self.done = true;
+
+        result.err().map(Err)
+    }
+}
+
+impl<'de, T: Deserialize<'de>, I: Input<'de>> FusedIterator for ListIter<'_, 'de, T, I> {}
+
+impl<T, I> Drop for ListIter<'_, '_, T, I> {
+    fn drop(&mut self) {
+        self.collection.de.depth -= 1;
+    }
+}
+
+/// Iterator over the entries of a dictionary, see
+/// [`Deserializer::iter_dict`].
+///
+/// It stops after the first error. Once it is over, the deserializer is
+/// past the end of the dictionary.
+pub struct DictIter<'a, 'de, K, V, I = BinaryStream<'de>> {
+    collection: BencodeCollection<'a, 'de, I>,
+    done: bool,
+    output: PhantomData<fn() -> (K, V)>,
+}
+
+impl<'de, K, V, I> Iterator for DictIter<'_, 'de, K, V, I>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    I: Input<'de>,
+{
+    type Item = Result<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = match de::MapAccess::next_entry(&mut self.collection) {
+            Ok(Some(entry)) => return Some(Ok(entry)),
+            Ok(None) => self.collection.de.expect_end(),
+            Err(err) => Err(err),
+        };
+        self.done = true;
+
+        result.err().map(Err)
+    }
+}
+
+impl<'de, K, V, I> FusedIterator for DictIter<'_, 'de, K, V, I>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    I: Input<'de>,
+{
+}
+
+impl<K, V, I> Drop for DictIter<'_, '_, K, V, I> {
+    fn drop(&mut self) {
+        self.collection.de.depth -= 1;
+    }
+}
+
+struct BencodeCollection<'a, 'de: 'a, I> {
+    de: &'a mut Deserializer<'de, I>,
+    // Current dictionary key, to check the order of the next one and for the
+    // path of errors in its value. Only copied when it can't be borrowed.
+    key: Vec<u8>,
+    borrowed_key: Option<&'de [u8]>,
+    len: usize,
+}
+
+impl<'a, 'de, I: Input<'de>> BencodeCollection<'a, 'de, I> {
+    fn new(de: &'a mut Deserializer<'de, I>) -> Self {
+        Self {
+            de,
+            key: Vec::new(),
+            borrowed_key: None,
+            len: 0,
+        }
+    }
+
+    /// Whether there is another list element, counting it if so.
+    fn next_element(&mut self) -> Result<bool> {
+        if self.de.input.try_peek()? == b'e' {
+            return Ok(false);
+        }
+
+        self.count()?;
+        Ok(true)
+    }
+
+    /// Reads the next dictionary key, see [`Self::key`], and returns where
+    /// it starts. `None` at the end of the dictionary.
+    fn next_key(&mut self) -> Result<Option<usize>> {
+        match self.de.input.try_peek()? {
+            b'e' => Ok(None),
+            b'0'..=b'9' => {
+                self.count()?;
+                let offset = self.de.input.position();
+                let check = self.de.options.canonical || self.de.options.lenient;
+                let key = self.de.parse_bytes()?;
+
+                let previous = self.borrowed_key.unwrap_or(&self.key);
+                let ordering = check.then(|| (*key).cmp(previous));
+                self.borrowed_key = match key {
+                    Reference::Borrowed(key) => Some(key),
+                    Reference::Copied(key) => {
+                        self.key.clear();
+                        self.key.extend_from_slice(key);
+                        None
+                    }
+                };
+
+                if let Some(ordering) = ordering {
+                    self.check_order(ordering, offset)?;
+                }
+
+                Ok(Some(offset))
+            }
+            _ => Err(ErrorKind::KeyMustBeString.at(self.de.input.position())),
+        }
+    }
+
+    fn skip_elements(&mut self) -> Result<()> {
+        while self.next_element()? {
+            let offset = self.de.input.position();
+            self.de
+                .skip_value()
+                .map_err(|e| e.or_offset(offset).in_index(self.len - 1))?;
+        }
+
+        Ok(())
+    }
+
+    fn skip_entries(&mut self) -> Result<()> {
+        while self.next_key()?.is_some() {
+            let offset = self.de.input.position();
+            self.de
+                .skip_value()
+                .map_err(|e| e.or_offset(offset).in_key(self.key()))?;
+        }
+
+        Ok(())
+    }
+
+    /// The dictionary key last read by [`Self::next_key`].
+    fn key(&self) -> &[u8] {
+        self.borrowed_key.unwrap_or(&self.key)
+    }
+
+    fn count(&mut self) -> Result<()> {
+        self.len += 1;
+        if self.len > self.de.options.limits.max_collection_len {
+            let offset = self.de.input.position();
+            return Err(ErrorKind::LimitExceeded(Limit::CollectionLen).at(offset));
+        }
+
+        Ok(())
+    }
+
+    /// Fails in canonical mode, and only warns in lenient mode.
+    /// `ordering` is the one of the current key to the previous one.
+    fn check_order(&mut self, ordering: Ordering, offset: usize) -> Result<()> {
+        if self.len == 1 {
+            return Ok(());
+        }
+
+        let key = self.key();
+        if self.de.options.canonical {
+            match ordering {
+                Ordering::Less => Err(ErrorKind::UnsortedKey(key.to_vec()).at(offset)),
+                Ordering::Equal => Err(ErrorKind::DuplicateKey(key.to_vec()).at(offset)),
+                Ordering::Greater => Ok(()),
+            }
+        } else {
+            match ordering {
+                Ordering::Less => self.de.warn(offset, WarningKind::UnsortedKey(key.to_vec())),
+                Ordering::Equal => self
+                    .de
+                    .warn(offset, WarningKind::DuplicateKey(key.to_vec())),
+                Ordering::Greater => {}
+            }
+            Ok(())
+        }
+    }
+}
+
+impl<'a, 'de, I: Input<'de>> de::SeqAccess<'de> for BencodeCollection<'a, 'de, I> {
     type Error = Error;
 
     fn next_element_seed<T>(
@@ -355,33 +1136,404 @@ impl<'a, 'de> de::SeqAccess<'de> for BencodeCollection<'a, 'de> {
     where
         T: de::DeserializeSeed<'de>,
     {
-        if self.de.input.try_peek()? == b'e' {
+        if !self.next_element()? {
             return Ok(None);
         }
 
-        seed.deserialize(&mut *self.de).map(Some)
+        let offset = self.de.input.position();
+
+        seed.deserialize(&mut *self.de)
+            .map(Some)
+            .map_err(|e| e.or_offset(offset).in_index(self.len - 1))
     }
 }
 
-impl<'a, 'de> de::MapAccess<'de> for BencodeCollection<'a, 'de> {
+impl<'a, 'de, I: Input<'de>> de::MapAccess<'de> for BencodeCollection<'a, 'de, I> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> std::result::Result<Option<K::Value>, Self::Error>
     where
         K: de::DeserializeSeed<'de>,
     {
-        if self.de.input.try_peek()? == b'e' {
+        let Some(offset) = self.next_key()? else {
+            return Ok(None);
+        };
+
+        let key = match self.borrowed_key {
+            Some(key) => Reference::Borrowed(key),
+            None => Reference::Copied(&self.key),
+        };
+
+        seed.deserialize(MapKey(key, self.de.options.string_decoding))
+            .map(Some)
+            .map_err(|e| e.or_offset(offset))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let offset = self.de.input.position();
+
+        seed.deserialize(&mut *self.de)
+            .map_err(|e| e.or_offset(offset).in_key(self.key()))
+    }
+}
+
+/// Visits `bytes` as a string if valid UTF-8, and as bytes otherwise.
+fn visit_any_bytes<'de, V>(bytes: Reference<'de, '_>, visitor: V) -> Result<V::Value>
+where
+    V: de::Visitor<'de>,
+{
+    match bytes {
+        Reference::Borrowed(bytes) => match from_utf8(bytes) {
+            Ok(str) => visitor.visit_borrowed_str(str),
+            Err(_) => visitor.visit_borrowed_bytes(bytes),
+        },
+        Reference::Copied(bytes) => match from_utf8(bytes) {
+            Ok(str) => visitor.visit_str(str),
+            Err(_) => visitor.visit_bytes(bytes),
+        },
+    }
+}
+
+/// Visits `bytes` as a string, decoded as configured if not valid UTF-8.
+fn visit_text<'de, V>(
+    bytes: Reference<'de, '_>,
+    decoding: StringDecoding,
+    visitor: V,
+) -> Result<V::Value>
+where
+    V: de::Visitor<'de>,
+{
+    match bytes {
+        Reference::Borrowed(bytes) => {
+            if let Ok(str) = from_utf8(bytes) {
+                return visitor.visit_borrowed_str(str);
+            }
+        }
+        Reference::Copied(bytes) => {
+            if let Ok(str) = from_utf8(bytes) {
+                return visitor.visit_str(str);
+            }
+        }
+    }
+
+    match decoding {
+        StringDecoding::Strict => Err(ErrorKind::InvalidString.into()),
+        StringDecoding::Lossy => visitor.visit_string(String::from_utf8_lossy(&bytes).into_owned()),
+        StringDecoding::Legacy(decode) => match decode(&bytes) {
+            Some(string) => visitor.visit_string(string),
+            None => Err(ErrorKind::InvalidString.into()),
+        },
+    }
+}
+
+/// Value standing in for a list of just that value, in lenient mode.
+struct Singleton<'a, 'de: 'a, I> {
+    de: &'a mut Deserializer<'de, I>,
+    done: bool,
+}
+
+impl<'a, 'de, I: Input<'de>> de::SeqAccess<'de> for Singleton<'a, 'de, I> {
+    type Error = Error;
+
+    fn next_element_seed<T>(
+        &mut self,
+        seed: T,
+    ) -> std::result::Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if self.done {
+            return Ok(None);
+        }
+        self.done = true;
+
+        seed.deserialize(&mut *self.de)
+            .map(Some)
+            .map_err(|e| e.in_index(0))
+    }
+}
+
+/// Dictionary key. Keys are always byte strings, but they can be read back
+/// as the integers or `u8` sequences they were written from (see
+/// `ser::MapKeySerializer`).
+struct MapKey<'de, 's>(Reference<'de, 's>, StringDecoding);
+
+impl MapKey<'_, '_> {
+    fn as_str(&self) -> Result<&str> {
+        from_utf8(&self.0).map_err(|_| ErrorKind::InvalidString.into())
+    }
+
+    fn parse<T: FromStr>(&self) -> Result<T> {
+        let str = self.as_str()?;
+
+        number::check_integer(str)?;
+
+        str.parse().map_err(|_| {
+            ErrorKind::IntegerOverflow {
+                number: str.to_string(),
+                target: type_name::<T>(),
+            }
+            .into()
+        })
+    }
+}
+
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident),*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+            where
+                V: de::Visitor<'de>,
+            {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for MapKey<'de, '_> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visit_any_bytes(self.0, visitor)
+    }
+
+    deserialize_parsed_key! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let str = self.as_str()?;
+
+        visitor.visit_char(str.parse().map_err(|_| {
+            <Error as de::Error>::invalid_value(de::Unexpected::Str(str), &"a single character")
+        })?)
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visit_text(self.0, self.1, visitor)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0 {
+            Reference::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Reference::Copied(bytes) => visitor.visit_bytes(bytes),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let invalid = |_| Error::from(ErrorKind::InvalidString);
+
+        match self.0 {
+            Reference::Borrowed(bytes) => {
+                let str = from_utf8(bytes).map_err(invalid)?;
+                visitor.visit_enum(BorrowedStrDeserializer::new(str))
+            }
+            Reference::Copied(bytes) => {
+                let str = from_utf8(bytes).map_err(invalid)?;
+                visitor.visit_enum(StrDeserializer::new(str))
+            }
+        }
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        SeqDeserializer::new(self.0.iter().copied()).deserialize_any(visitor)
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool f32 f64 unit unit_struct tuple_struct map struct ignored_any
+    }
+}
+
+/// Integer that doesn't fit into any serde primitive, handed out as a map with
+/// a single private key, see [`crate::Number`].
+struct BigNumber<'a> {
+    digits: &'a str,
+    key_visited: bool,
+}
+
+impl<'de> de::MapAccess<'de> for BigNumber<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> std::result::Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if self.key_visited {
             return Ok(None);
         }
+        self.key_visited = true;
 
-        seed.deserialize(&mut *self.de).map(Some)
+        seed.deserialize(BorrowedStrDeserializer::new(number::TOKEN))
+            .map(Some)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> std::result::Result<V::Value, Self::Error>
     where
         V: de::DeserializeSeed<'de>,
     {
+        seed.deserialize(StrDeserializer::new(self.digits))
+    }
+}
+
+/// Non-unit variant of an externally tagged enum: a dictionary with the
+/// variant name as its only key.
+struct BencodeEnum<'a, 'de: 'a, I> {
+    de: &'a mut Deserializer<'de, I>,
+    // Copy of the variant name, for the path of errors in its value.
+    variant: Vec<u8>,
+}
+
+impl<'a, 'de, I: Input<'de>> de::EnumAccess<'de> for BencodeEnum<'a, 'de, I> {
+    type Error = Error;
+
+    type Variant = Self;
+
+    fn variant_seed<V>(
+        mut self,
+        seed: V,
+    ) -> std::result::Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let offset = self.de.input.position();
+        let decoding = self.de.options.string_decoding;
+        let name = self.de.parse_bytes()?;
+        self.variant = name.to_vec();
+        let variant = seed
+            .deserialize(MapKey(name, decoding))
+            .map_err(|e| e.or_offset(offset))?;
+
+        Ok((variant, self))
+    }
+}
+
+impl<'de, I: Input<'de>> de::VariantAccess<'de> for BencodeEnum<'_, 'de, I> {
+    type Error = Error;
+
+    fn unit_variant(self) -> std::result::Result<(), Self::Error> {
+        let err: Error = de::Error::invalid_type(de::Unexpected::NewtypeVariant, &"unit variant");
+
+        Err(err.or_offset(self.de.input.position()))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> std::result::Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let offset = self.de.input.position();
+
         seed.deserialize(&mut *self.de)
+            .map_err(|e| e.or_offset(offset).in_key(&self.variant))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let offset = self.de.input.position();
+
+        de::Deserializer::deserialize_seq(&mut *self.de, visitor)
+            .map_err(|e| e.or_offset(offset).in_key(&self.variant))
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let offset = self.de.input.position();
+
+        de::Deserializer::deserialize_map(&mut *self.de, visitor)
+            .map_err(|e| e.or_offset(offset).in_key(&self.variant))
     }
 }
 
@@ -0,0 +1,74 @@
+//! Encodings for Rust types that have no direct bencode counterpart. The
+//! [`Serializer`](crate::Serializer) and [`Deserializer`](crate::Deserializer)
+//! have to be configured with the same choice for data to round-trip.
+//!
+//! When decoding, the options are not applied inside internally tagged or
+//! untagged enums, or structs with `#[serde(flatten)]` fields: serde reads
+//! those into its own buffer first, and decodes them without the options.
+//! Fields there can use the modules in [`with`](crate::with) instead, and an
+//! omitted `Option` field is still read back as `None`.
+
+/// How an `Option` is written where it can't be left out. `None` struct
+/// fields are always omitted together with their key, but not those of a
+/// `#[serde(flatten)]` struct, which serde writes as map entries: mark them
+/// `#[serde(skip_serializing_if = "Option::is_none")]`.
+///
+/// Not applied everywhere when decoding, see the [module
+/// documentation](crate::encoding).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OptionEncoding {
+    /// `Some(v)` is written as `v`, and `None` is an error.
+    #[default]
+    Bare,
+    /// `None` is written as `le` and `Some(v)` as `l<v>e`, so even nested
+    /// options round-trip.
+    List,
+}
+
+/// How a `bool` is written.
+///
+/// Not applied everywhere when decoding, such as to the `ro` flag of a KRPC
+/// message, see the [module documentation](crate::encoding).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BoolEncoding {
+    /// Booleans are rejected, bencode has no such type.
+    #[default]
+    Unsupported,
+    /// `true` is written as `i1e` and `false` as `i0e`, the way flags such as
+    /// `private` are stored in metainfo files. Any other integer is rejected
+    /// when decoding.
+    Integer,
+}
+
+/// How `f32` and `f64` are written.
+///
+/// Not applied everywhere when decoding, see the [module
+/// documentation](crate::encoding).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatEncoding {
+    /// Floats are rejected, bencode has no such type.
+    #[default]
+    Unsupported,
+    /// A byte string holding the shortest decimal representation that parses
+    /// back to the same value, e.g. `3:0.5`.
+    Decimal,
+    /// A byte string holding the IEEE-754 big-endian bytes: 4 for `f32` and
+    /// 8 for `f64`.
+    Binary,
+}
+
+/// How byte strings that are not valid UTF-8 are read into `str` and
+/// `String`. Valid UTF-8 is always borrowed where the target allows it, e.g.
+/// `&str` or `#[serde(borrow)] Cow<str>`. Only used when deserializing.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum StringDecoding {
+    /// Invalid UTF-8 is an error.
+    #[default]
+    Strict,
+    /// Invalid sequences are replaced with U+FFFD, which needs an owned
+    /// target such as `String` or `Cow<str>`.
+    Lossy,
+    /// Invalid UTF-8 is handed to this decoder, e.g. for Latin-1 or GBK names
+    /// in old torrents. `None` is an error.
+    Legacy(fn(&[u8]) -> Option<String>),
+}
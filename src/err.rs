@@ -1,30 +1,204 @@
-use std::fmt::Display;
+use std::fmt::{self, Display};
 
 use serde::{de, ser};
 
+use crate::Limit;
+
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Error together with, when it comes from the deserializer, the byte offset
+/// it was found at and the path of dictionary keys and list indices to it.
+pub struct Error(Box<ErrorImpl>);
+
+struct ErrorImpl {
+    kind: ErrorKind,
+    offset: Option<usize>,
+    // Innermost segment first, they are added while the error unwinds.
+    path: Vec<Segment>,
+}
+
+enum Segment {
+    Key(Vec<u8>),
+    Index(usize),
+}
+
 #[derive(Debug)]
-pub enum Error {
+pub enum ErrorKind {
     Message(String),
+    /// Reading the input failed.
+    Io(std::io::Error),
 
-    ExpectedNumber,
-    ExpectedString,
-    ExpectedList,
-    ExpectedDictionary,
+    /// Found a value of the wrong type, both described in bencode terms.
+    InvalidType {
+        expected: String,
+        found: String,
+    },
+    /// Found a value of the right type that is not acceptable.
+    InvalidValue {
+        expected: String,
+        found: String,
+    },
+    LeadingZero,
+    NegativeZero,
+    IntegerOverflow {
+        number: String,
+        target: &'static str,
+    },
+    MissingTerminator,
+    NegativeLength,
     ExpectedEnd,
+    TrailingData,
     InvalidString,
+    InvalidBool(i64),
+    InvalidFloat,
+    KeyMustBeString,
+    DuplicateKey(Vec<u8>),
+    UnsortedKey(Vec<u8>),
+    LimitExceeded(Limit),
     TypeNotSupported,
+    NoneNotSupported,
+    Syntax,
+    /// The input ended early, at least `needed` more bytes are required.
+    Eof {
+        needed: usize,
+    },
+}
+
+/// Broad kind of an [`Error`], see [`Error::classify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// Reading the input failed.
+    Io,
+    /// The input is not valid bencode.
     Syntax,
+    /// The input is valid bencode that does not fit the target type, or a
+    /// value can't be serialized.
+    Data,
+    /// The input ended before the value did, more of it may fix that.
     Eof,
 }
 
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.0.kind
+    }
+
+    pub fn classify(&self) -> Category {
+        match self.0.kind {
+            ErrorKind::Io(_) => Category::Io,
+            ErrorKind::Eof { .. } => Category::Eof,
+            ErrorKind::LeadingZero
+            | ErrorKind::NegativeZero
+            | ErrorKind::MissingTerminator
+            | ErrorKind::NegativeLength
+            | ErrorKind::ExpectedEnd
+            | ErrorKind::TrailingData
+            | ErrorKind::KeyMustBeString
+            | ErrorKind::DuplicateKey(_)
+            | ErrorKind::UnsortedKey(_)
+            | ErrorKind::Syntax => Category::Syntax,
+            ErrorKind::Message(_)
+            | ErrorKind::InvalidType { .. }
+            | ErrorKind::InvalidValue { .. }
+            | ErrorKind::IntegerOverflow { .. }
+            | ErrorKind::InvalidString
+            | ErrorKind::InvalidBool(_)
+            | ErrorKind::InvalidFloat
+            | ErrorKind::LimitExceeded(_)
+            | ErrorKind::TypeNotSupported
+            | ErrorKind::NoneNotSupported => Category::Data,
+        }
+    }
+
+    pub fn is_io(&self) -> bool {
+        self.classify() == Category::Io
+    }
+
+    pub fn is_syntax(&self) -> bool {
+        self.classify() == Category::Syntax
+    }
+
+    pub fn is_data(&self) -> bool {
+        self.classify() == Category::Data
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.classify() == Category::Eof
+    }
+
+    /// Minimum number of bytes the input is missing, for [`Category::Eof`].
+    pub fn needed(&self) -> Option<usize> {
+        match self.0.kind {
+            ErrorKind::Eof { needed } => Some(needed),
+            _ => None,
+        }
+    }
+
+    /// Offset in the input of the invalid byte, or of the start of the value
+    /// that could not be deserialized.
+    pub fn offset(&self) -> Option<usize> {
+        self.0.offset
+    }
+
+    /// Path to the invalid value, like `info.files[17].length`. Empty when
+    /// the error is about the top-level value.
+    pub fn path(&self) -> String {
+        let mut path = String::new();
+
+        for segment in self.0.path.iter().rev() {
+            match segment {
+                Segment::Key(key) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path.push_str(&String::from_utf8_lossy(key));
+                }
+                Segment::Index(index) => path.push_str(&format!("[{index}]")),
+            }
+        }
+
+        path
+    }
+
+    /// Sets the offset unless a more precise one is already known.
+    pub(crate) fn or_offset(mut self, offset: usize) -> Self {
+        self.0.offset.get_or_insert(offset);
+        self
+    }
+
+    pub(crate) fn in_key(mut self, key: &[u8]) -> Self {
+        self.0.path.push(Segment::Key(key.to_vec()));
+        self
+    }
+
+    pub(crate) fn in_index(mut self, index: usize) -> Self {
+        self.0.path.push(Segment::Index(index));
+        self
+    }
+}
+
+impl ErrorKind {
+    pub(crate) fn at(self, offset: usize) -> Error {
+        Error::from(self).or_offset(offset)
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error(Box::new(ErrorImpl {
+            kind,
+            offset: None,
+            path: Vec::new(),
+        }))
+    }
+}
+
 impl ser::Error for Error {
     fn custom<T>(msg: T) -> Self
     where
         T: Display,
     {
-        Error::Message(msg.to_string())
+        ErrorKind::Message(msg.to_string()).into()
     }
 }
 
@@ -33,25 +207,123 @@ impl de::Error for Error {
     where
         T: Display,
     {
-        Error::Message(msg.to_string())
+        ErrorKind::Message(msg.to_string()).into()
+    }
+
+    fn invalid_type(unexp: de::Unexpected, exp: &dyn de::Expected) -> Self {
+        ErrorKind::InvalidType {
+            expected: exp.to_string(),
+            found: describe(unexp),
+        }
+        .into()
+    }
+
+    fn invalid_value(unexp: de::Unexpected, exp: &dyn de::Expected) -> Self {
+        ErrorKind::InvalidValue {
+            expected: exp.to_string(),
+            found: describe(unexp),
+        }
+        .into()
     }
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// What serde found, in terms of the bencode it was read from.
+fn describe(unexp: de::Unexpected) -> String {
+    use de::Unexpected;
+
+    match unexp {
+        Unexpected::Bool(b) => format!("boolean {b}"),
+        Unexpected::Unsigned(n) => format!("integer {n}"),
+        Unexpected::Signed(n) => format!("integer {n}"),
+        Unexpected::Float(n) => format!("float {n}"),
+        Unexpected::Char(c) => format!("byte string {c:?}"),
+        Unexpected::Str(s) => format!("byte string of length {}", s.len()),
+        Unexpected::Bytes(b) => format!("byte string of length {}", b.len()),
+        Unexpected::Unit => "empty list".to_string(),
+        Unexpected::Seq => "list".to_string(),
+        Unexpected::Map => "dictionary".to_string(),
+        unexp => unexp.to_string(),
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Error::Message(m) => f.write_str(m),
-            Error::Eof => write!(f, "unexpected end of file"),
-            Error::ExpectedNumber => write!(f, "expected number"),
-            Error::ExpectedString => write!(f, "expected string"),
-            Error::ExpectedList => write!(f, "expected list"),
-            Error::ExpectedDictionary => write!(f, "expected dictionary"),
-            Error::TypeNotSupported => write!(f, "type is not supported in BENCODE format"),
-            Error::ExpectedEnd => write!(f, "expected end"),
-            Error::InvalidString => write!(f, "string is not valid"),
-            Error::Syntax => write!(f, "syntax error"),
+            ErrorKind::Message(m) => f.write_str(m),
+            ErrorKind::Io(err) => write!(f, "error while reading the input: {err}"),
+            ErrorKind::Eof { needed } => write!(
+                f,
+                "unexpected end of file, at least {needed} more bytes are needed"
+            ),
+            ErrorKind::InvalidType { expected, found } => {
+                write!(f, "expected {expected}, found {found}")
+            }
+            ErrorKind::InvalidValue { expected, found } => {
+                write!(f, "invalid value: expected {expected}, found {found}")
+            }
+            ErrorKind::LeadingZero => write!(f, "number has a leading zero"),
+            ErrorKind::NegativeZero => write!(f, "`-0` is not a valid number"),
+            ErrorKind::IntegerOverflow { number, target } => {
+                write!(f, "integer {number} is out of range for {target}")
+            }
+            ErrorKind::MissingTerminator => write!(f, "number is not followed by its terminator"),
+            ErrorKind::NegativeLength => write!(f, "byte string length is negative"),
+            ErrorKind::TypeNotSupported => write!(f, "type is not supported in BENCODE format"),
+            ErrorKind::NoneNotSupported => {
+                write!(f, "`None` can only be omitted as a struct field")
+            }
+            ErrorKind::ExpectedEnd => write!(f, "expected end"),
+            ErrorKind::TrailingData => write!(f, "trailing data after the value"),
+            ErrorKind::InvalidString => write!(f, "string is not valid"),
+            ErrorKind::InvalidBool(n) => write!(f, "expected 0 or 1 for boolean, found {n}"),
+            ErrorKind::InvalidFloat => write!(f, "byte string is not a valid float"),
+            ErrorKind::KeyMustBeString => write!(f, "dictionary key must be a byte string"),
+            ErrorKind::DuplicateKey(key) => write!(
+                f,
+                "duplicate dictionary key {:?}",
+                String::from_utf8_lossy(key)
+            ),
+            ErrorKind::UnsortedKey(key) => write!(
+                f,
+                "dictionary key {:?} is out of order",
+                String::from_utf8_lossy(key)
+            ),
+            ErrorKind::LimitExceeded(limit) => write!(f, "{limit} limit exceeded"),
+            ErrorKind::Syntax => write!(f, "syntax error"),
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0.kind, f)?;
+
+        if !self.0.path.is_empty() {
+            write!(f, " at `{}`", self.path())?;
+        }
+        if let Some(offset) = self.0.offset {
+            write!(f, " (byte {offset})")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Error")
+            .field("kind", &self.0.kind)
+            .field("offset", &self.0.offset)
+            .field("path", &self.path())
+            .finish()
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.0.kind {
+            ErrorKind::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
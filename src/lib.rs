@@ -1,17 +1,32 @@
 mod de;
 mod err;
+mod limits;
+mod number;
+mod push;
 mod ser;
+mod stream;
 mod value;
+mod warning;
 
-pub(crate) mod stream;
+pub mod encoding;
+pub mod with;
 
-pub use de::from_binary;
-pub use err::{Error, Result};
-pub use ser::to_binary;
+pub use de::{
+    from_binary, from_binary_lenient, from_binary_prefix, from_reader, is_canonical, Deserializer,
+    DictIter, ListIter, StreamDeserializer,
+};
+pub use encoding::{BoolEncoding, FloatEncoding, OptionEncoding, StringDecoding};
+pub use err::{Category, Error, ErrorKind, Result};
+pub use limits::{Limit, Limits};
+pub use number::Number;
+pub use push::{PushParser, Status};
+pub use ser::{to_binary, MapOrder, Serializer};
+pub use stream::ReaderStream;
 pub use value::Value;
+pub use warning::{Warning, WarningKind};
 
 pub mod types {
-    pub type Number = i64;
+    pub type Number = crate::Number;
     pub type String = std::string::String;
     pub type List<T> = Vec<T>;
     pub type Dictionary<T> = std::collections::BTreeMap<String, T>;
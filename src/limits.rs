@@ -0,0 +1,64 @@
+//! Bounds on what the [`Deserializer`](crate::Deserializer) accepts, for
+//! parsing input from untrusted peers and trackers.
+
+use std::fmt::{self, Display};
+
+/// Limits enforced while deserializing. Only nesting depth is bounded by
+/// default, everything else is only bounded by the size of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Lists, dictionaries and enum variants nested in one another.
+    pub max_depth: usize,
+    /// Length of a single byte string.
+    pub max_bytes_len: usize,
+    /// Elements of a single list, or entries of a single dictionary.
+    pub max_collection_len: usize,
+    /// Length of all byte strings together, keys included.
+    pub max_total_bytes: usize,
+    /// Digits of a single integer, or of the length of a byte string.
+    pub max_integer_len: usize,
+}
+
+impl Limits {
+    /// No limit at all, not even on depth.
+    pub const fn none() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            max_bytes_len: usize::MAX,
+            max_collection_len: usize::MAX,
+            max_total_bytes: usize::MAX,
+            max_integer_len: usize::MAX,
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            ..Self::none()
+        }
+    }
+}
+
+/// Which of the [`Limits`] was exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Depth,
+    BytesLen,
+    CollectionLen,
+    TotalBytes,
+    IntegerLen,
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Depth => write!(f, "nesting depth"),
+            Limit::BytesLen => write!(f, "byte string length"),
+            Limit::CollectionLen => write!(f, "collection length"),
+            Limit::TotalBytes => write!(f, "total byte string length"),
+            Limit::IntegerLen => write!(f, "integer length"),
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use serde::{de, ser};
+
+use crate::{Error, ErrorKind};
+
+/// Name of the newtype struct (when serializing) and of the single map key
+/// (when deserializing) that carry integers too big for any serde primitive.
+pub(crate) const TOKEN: &str = "$serde_bencode::private::Number";
+
+/// Bencode integer of any size.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Number(N);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum N {
+    Int(i128),
+    // Decimal digits (with an optional `-`) of a value outside of `i128`.
+    Big(Box<str>),
+}
+
+impl Number {
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_i128()?.try_into().ok()
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_i128()?.try_into().ok()
+    }
+
+    pub fn as_i128(&self) -> Option<i128> {
+        match self.0 {
+            N::Int(n) => Some(n),
+            N::Big(_) => None,
+        }
+    }
+
+    pub fn as_u128(&self) -> Option<u128> {
+        match &self.0 {
+            N::Int(n) => (*n).try_into().ok(),
+            N::Big(digits) => digits.parse().ok(),
+        }
+    }
+
+    fn is_negative(&self) -> bool {
+        match &self.0 {
+            N::Int(n) => *n < 0,
+            N::Big(digits) => digits.starts_with('-'),
+        }
+    }
+}
+
+macro_rules! impl_from_primitive {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Number {
+                fn from(n: $t) -> Self {
+                    Number(N::Int(n.into()))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_primitive!(i8, i16, i32, i64, i128, u8, u16, u32, u64);
+
+impl From<u128> for Number {
+    fn from(n: u128) -> Self {
+        match n.try_into() {
+            Ok(n) => Number(N::Int(n)),
+            Err(_) => Number(N::Big(n.to_string().into())),
+        }
+    }
+}
+
+impl FromStr for Number {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        check_integer(s)?;
+
+        match s.parse() {
+            Ok(n) => Ok(Number(N::Int(n))),
+            Err(_) => Ok(Number(N::Big(s.into()))),
+        }
+    }
+}
+
+/// Checks that `s` is written the way BEP 3 requires of integers: decimal
+/// digits with an optional `-`, no leading zero and no `-0`.
+pub(crate) fn check_integer(s: &str) -> crate::Result<()> {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ErrorKind::InvalidValue {
+            expected: "an integer".to_string(),
+            found: format!("byte string {s:?}"),
+        }
+        .into());
+    }
+    if digits.len() > 1 && digits.starts_with('0') {
+        return Err(ErrorKind::LeadingZero.into());
+    }
+    if s == "-0" {
+        return Err(ErrorKind::NegativeZero.into());
+    }
+
+    Ok(())
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            N::Int(n) => Display::fmt(n, f),
+            N::Big(digits) => f.write_str(digits),
+        }
+    }
+}
+
+impl fmt::Debug for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Number")
+            .field(&format_args!("{self}"))
+            .finish()
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.0, &other.0) {
+            (N::Int(a), N::Int(b)) => a.cmp(b),
+            // A big number is further away from zero than any `i128`.
+            (N::Big(_), N::Int(_)) if self.is_negative() => Ordering::Less,
+            (N::Big(_), N::Int(_)) => Ordering::Greater,
+            (N::Int(_), N::Big(_)) => other.cmp(self).reverse(),
+            (N::Big(a), N::Big(b)) => match (self.is_negative(), other.is_negative()) {
+                (false, true) => Ordering::Greater,
+                (true, false) => Ordering::Less,
+                (false, false) => a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+                (true, true) => b.len().cmp(&a.len()).then_with(|| b.cmp(a)),
+            },
+        }
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ser::Serialize for Number {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match &self.0 {
+            N::Int(n) => {
+                if let Ok(n) = i64::try_from(*n) {
+                    serializer.serialize_i64(n)
+                } else if let Ok(n) = u64::try_from(*n) {
+                    serializer.serialize_u64(n)
+                } else {
+                    serializer.serialize_i128(*n)
+                }
+            }
+            N::Big(digits) => serializer.serialize_newtype_struct(TOKEN, &**digits),
+        }
+    }
+}
+
+impl<'de> de::Deserialize<'de> for Number {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(NumberVisitor)
+    }
+}
+
+struct NumberVisitor;
+
+impl<'de> de::Visitor<'de> for NumberVisitor {
+    type Value = Number;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an integer")
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        match map.next_key::<String>()? {
+            Some(key) if key == TOKEN => visit_digits(map),
+            _ => Err(de::Error::invalid_type(de::Unexpected::Map, &self)),
+        }
+    }
+}
+
+/// Reads the digits of a big number once its [`TOKEN`] key has been read.
+pub(crate) fn visit_digits<'de, A>(mut map: A) -> Result<Number, A::Error>
+where
+    A: de::MapAccess<'de>,
+{
+    let digits: String = map.next_value()?;
+
+    digits.parse().map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Number;
+    use crate::ErrorKind;
+
+    #[test]
+    fn test_ordering() {
+        let numbers: Vec<Number> = [
+            "-99999999999999999999999999999999999999999",
+            "-9999999999999999999999999999999999999999",
+            "-5",
+            "0",
+            "7",
+            "340282366920938463463374607431768211455",
+            "9999999999999999999999999999999999999999",
+            "10000000000000000000000000000000000000000",
+        ]
+        .iter()
+        .map(|n| n.parse().unwrap())
+        .collect();
+
+        assert!(numbers.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_big_number_must_be_canonical() {
+        let err = "-000340282366920938463463374607431768211456"
+            .parse::<Number>()
+            .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::LeadingZero));
+
+        let number: Number = "-340282366920938463463374607431768211456".parse().unwrap();
+        assert_eq!(
+            number.to_string(),
+            "-340282366920938463463374607431768211456"
+        );
+        assert_eq!(number.as_u128(), None);
+    }
+}
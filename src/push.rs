@@ -0,0 +1,330 @@
+//! Parsing values that arrive a chunk at a time, such as messages read from
+//! a socket, without waiting for or re-reading the whole input.
+
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+
+use crate::{Deserializer, ErrorKind, Limit, Limits, Result};
+
+/// Outcome of [`PushParser::feed`].
+#[derive(Debug, PartialEq)]
+pub enum Status<T> {
+    /// The value is not complete yet, at least `needed` more bytes are.
+    NeedMore { needed: usize },
+    /// The value is complete, and was `consumed` bytes long.
+    Complete { value: T, consumed: usize },
+}
+
+/// Resumable parser for values of type `T` written back to back.
+///
+/// Each chunk is scanned once for where the value ends, keeping track of
+/// the open lists and dictionaries and of the byte string being read, and
+/// the value is only deserialized once it is complete. Bytes past its end
+/// are kept for the next value, feed an empty chunk to get to it.
+///
+/// Offsets in errors count from the start of the value. A value that is
+/// valid bencode but does not fit `T` is dropped, and parsing goes on with
+/// the next one. After any other error the input is out of sync, and
+/// everything buffered is dropped.
+pub struct PushParser<T> {
+    buffer: Vec<u8>,
+    scanner: Scanner,
+    limits: Limits,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> PushParser<T> {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            scanner: Scanner::default(),
+            limits: Limits::default(),
+            output: PhantomData,
+        }
+    }
+
+    /// Sets the limits to enforce, see [`Limits`]. They are checked while
+    /// scanning, so that a value that never ends can't buffer without bound.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Bytes received but not yet returned as part of a value.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Status<T>> {
+        self.buffer.extend_from_slice(chunk);
+
+        match self.scanner.scan(&self.buffer, &self.limits) {
+            Ok(Some(end)) => {
+                let value = self.deserialize(end);
+                self.buffer.drain(..end);
+                self.scanner = Scanner::default();
+
+                Ok(Status::Complete {
+                    value: value?,
+                    consumed: end,
+                })
+            }
+            Ok(None) => Ok(Status::NeedMore {
+                needed: self.scanner.needed(),
+            }),
+            Err(fallback) => {
+                // The deserializer describes the error best, and reaches it
+                // since the value is broken within what has been buffered,
+                // unless `T` gives up on it earlier with a data error.
+                let err = match self.deserialize(self.buffer.len()) {
+                    Err(err)
+                        if err.is_syntax() || matches!(err.kind(), ErrorKind::LimitExceeded(_)) =>
+                    {
+                        err
+                    }
+                    _ => fallback.at(self.scanner.pos),
+                };
+                self.buffer.clear();
+                self.scanner = Scanner::default();
+
+                Err(err)
+            }
+        }
+    }
+
+    fn deserialize(&self, end: usize) -> Result<T> {
+        let mut deserializer = Deserializer::new(&self.buffer[..end]).limits(self.limits);
+        let value = T::deserialize(&mut deserializer).map_err(|e| e.or_offset(0))?;
+        deserializer.end()?;
+
+        Ok(value)
+    }
+}
+
+impl<T: DeserializeOwned> Default for PushParser<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where the value being scanned ends, found without deserializing it.
+#[derive(Default)]
+struct Scanner {
+    // Offset of the next byte to scan.
+    pos: usize,
+    token: Token,
+    stack: Vec<Frame>,
+    total_bytes: usize,
+}
+
+#[derive(Default, Clone, Copy)]
+enum Token {
+    /// Between values.
+    #[default]
+    Start,
+    Integer {
+        signed: bool,
+        digits: usize,
+    },
+    Length {
+        len: usize,
+        digits: usize,
+    },
+    Bytes {
+        remaining: usize,
+    },
+}
+
+struct Frame {
+    dict: bool,
+    // Whether the next value is a key, never the case in a list.
+    key_next: bool,
+    len: usize,
+}
+
+impl Scanner {
+    /// Offset just past the value once it is complete. Fails with what is
+    /// wrong at `self.pos`.
+    fn scan(
+        &mut self,
+        data: &[u8],
+        limits: &Limits,
+    ) -> std::result::Result<Option<usize>, ErrorKind> {
+        while self.pos < data.len() {
+            let b = data[self.pos];
+
+            match self.token {
+                Token::Bytes { remaining } => {
+                    let n = remaining.min(data.len() - self.pos);
+                    self.pos += n;
+                    self.token = Token::Bytes {
+                        remaining: remaining - n,
+                    };
+                    if remaining == n && self.end_value() {
+                        return Ok(Some(self.pos));
+                    }
+                    continue;
+                }
+                Token::Integer { signed, digits } => match b {
+                    b'-' if !signed && digits == 0 => {
+                        self.token = Token::Integer {
+                            signed: true,
+                            digits,
+                        }
+                    }
+                    b'0'..=b'9' => {
+                        if digits >= limits.max_integer_len {
+                            return Err(ErrorKind::LimitExceeded(Limit::IntegerLen));
+                        }
+                        self.token = Token::Integer {
+                            signed,
+                            digits: digits + 1,
+                        }
+                    }
+                    b'e' if digits > 0 => {
+                        self.pos += 1;
+                        if self.end_value() {
+                            return Ok(Some(self.pos));
+                        }
+                        continue;
+                    }
+                    _ => return Err(ErrorKind::Syntax),
+                },
+                Token::Length { len, digits } => match b {
+                    b'0'..=b'9' => {
+                        if digits >= limits.max_integer_len {
+                            return Err(ErrorKind::LimitExceeded(Limit::IntegerLen));
+                        }
+                        let len = len
+                            .checked_mul(10)
+                            .and_then(|len| len.checked_add(usize::from(b - b'0')))
+                            .ok_or(ErrorKind::Syntax)?;
+                        self.token = Token::Length {
+                            len,
+                            digits: digits + 1,
+                        };
+                    }
+                    b':' => {
+                        if len > limits.max_bytes_len {
+                            return Err(ErrorKind::LimitExceeded(Limit::BytesLen));
+                        }
+                        self.total_bytes = self.total_bytes.saturating_add(len);
+                        if self.total_bytes > limits.max_total_bytes {
+                            return Err(ErrorKind::LimitExceeded(Limit::TotalBytes));
+                        }
+                        self.token = Token::Bytes { remaining: len };
+                        if len == 0 {
+                            self.pos += 1;
+                            if self.end_value() {
+                                return Ok(Some(self.pos));
+                            }
+                            continue;
+                        }
+                    }
+                    _ => return Err(ErrorKind::Syntax),
+                },
+                Token::Start => {
+                    let key_next = self.stack.last().is_some_and(|frame| frame.key_next);
+
+                    match b {
+                        b'e' if self.stack.last().is_some_and(|f| !f.dict || f.key_next) => {
+                            self.stack.pop();
+                            self.pos += 1;
+                            if self.end_value() {
+                                return Ok(Some(self.pos));
+                            }
+                            continue;
+                        }
+                        b'0'..=b'9' => {
+                            self.count(limits)?;
+                            self.token = Token::Length {
+                                len: usize::from(b - b'0'),
+                                digits: 1,
+                            };
+                        }
+                        _ if key_next => return Err(ErrorKind::KeyMustBeString),
+                        b'i' => {
+                            self.count(limits)?;
+                            self.token = Token::Integer {
+                                signed: false,
+                                digits: 0,
+                            };
+                        }
+                        b'l' | b'd' => {
+                            self.count(limits)?;
+                            if self.stack.len() >= limits.max_depth {
+                                return Err(ErrorKind::LimitExceeded(Limit::Depth));
+                            }
+                            self.stack.push(Frame {
+                                dict: b == b'd',
+                                key_next: b == b'd',
+                                len: 0,
+                            });
+                        }
+                        _ => return Err(ErrorKind::Syntax),
+                    }
+                }
+            }
+
+            self.pos += 1;
+        }
+
+        Ok(None)
+    }
+
+    /// Counts a new element of the enclosing collection, keys included.
+    fn count(&mut self, limits: &Limits) -> std::result::Result<(), ErrorKind> {
+        if let Some(frame) = self.stack.last_mut() {
+            if frame.dict && !frame.key_next {
+                return Ok(());
+            }
+            frame.len += 1;
+            if frame.len > limits.max_collection_len {
+                return Err(ErrorKind::LimitExceeded(Limit::CollectionLen));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Called after a value ended. Whether it was the top-level one.
+    fn end_value(&mut self) -> bool {
+        self.token = Token::Start;
+
+        match self.stack.last_mut() {
+            None => true,
+            Some(frame) => {
+                if frame.dict {
+                    frame.key_next = !frame.key_next;
+                }
+                false
+            }
+        }
+    }
+
+    /// Lower bound of the bytes still missing: the rest of the current
+    /// token, the value owed to a key, and the end of every open collection.
+    fn needed(&self) -> usize {
+        let frame = self.stack.last();
+        let in_key = frame.is_some_and(|f| f.dict && f.key_next);
+
+        let token = match self.token {
+            // Unless the collection can end right here, a value is due, and
+            // the shortest ones are `0:`, `le` and `de`.
+            Token::Start if in_key || frame.is_some_and(|f| !f.dict) => 0,
+            Token::Start => 2,
+            Token::Integer { digits: 0, .. } => 2,
+            Token::Integer { .. } => 1,
+            Token::Length { len, .. } => 1 + len,
+            Token::Bytes { remaining } => remaining,
+        };
+        let value = match self.token {
+            Token::Start => 0,
+            _ if in_key => 2,
+            _ => 0,
+        };
+
+        token + value + self.stack.len()
+    }
+}
@@ -1,48 +1,239 @@
-use crate::{Error, Result};
-use serde::ser::{self, Serialize};
+use crate::number;
+use crate::{BoolEncoding, Error, ErrorKind, FloatEncoding, OptionEncoding, Result};
+use serde::ser::{self, Impossible, Serialize};
+use std::cmp::Ordering;
+use std::fmt::Display;
 
 pub fn to_binary<S: Serialize>(data: &S) -> Result<Vec<u8>> {
     let mut serializer = Serializer::new();
 
     data.serialize(&mut serializer)?;
-    Ok(serializer.container)
+    Ok(serializer.into_inner())
+}
+
+/// How [`Serializer`] orders the entries of maps (structs are always sorted).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MapOrder {
+    /// Buffer the entries and emit them sorted by raw key bytes.
+    #[default]
+    Sort,
+    /// Write the entries as they come and fail on the first key that is not
+    /// strictly greater than the previous one. Avoids buffering for callers
+    /// whose maps are already sorted (e.g. `BTreeMap<String, _>`).
+    ///
+    /// Maps of unknown length are still sorted, as serde writes structs with
+    /// `#[serde(flatten)]` fields as such maps, in declaration order.
+    Verify,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Options {
+    map_order: MapOrder,
+    option_encoding: OptionEncoding,
+    bool_encoding: BoolEncoding,
+    float_encoding: FloatEncoding,
 }
 
 pub struct Serializer {
     container: Vec<u8>,
+    options: Options,
+    // Set while writing a struct field, where `None` is omitted with its key.
+    omit_none: bool,
+    none_omitted: bool,
 }
 
 impl Serializer {
     pub fn new() -> Self {
         Self {
             container: Vec::new(),
+            options: Options::default(),
+            omit_none: false,
+            none_omitted: false,
+        }
+    }
+
+    /// Sets how map entries are ordered, see [`MapOrder`].
+    pub fn map_order(mut self, order: MapOrder) -> Self {
+        self.options.map_order = order;
+        self
+    }
+
+    /// Sets how options that can't be omitted are written, see [`OptionEncoding`].
+    pub fn option_encoding(mut self, encoding: OptionEncoding) -> Self {
+        self.options.option_encoding = encoding;
+        self
+    }
+
+    /// Sets how booleans are written, see [`BoolEncoding`].
+    pub fn bool_encoding(mut self, encoding: BoolEncoding) -> Self {
+        self.options.bool_encoding = encoding;
+        self
+    }
+
+    /// Sets how floats are written, see [`FloatEncoding`].
+    pub fn float_encoding(mut self, encoding: FloatEncoding) -> Self {
+        self.options.float_encoding = encoding;
+        self
+    }
+
+    /// Returns the bencode written so far.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.container
+    }
+
+    /// Serializer with the same options writing into its own buffer.
+    fn child(&self) -> Self {
+        Self {
+            options: self.options,
+            ..Self::new()
         }
     }
 
-    fn push_bytes(&mut self, bytes: &[u8]) -> () {
+    fn push_bytes(&mut self, bytes: &[u8]) {
         self.container.extend_from_slice(bytes);
     }
 
-    fn push_str(&mut self, string: String) -> () {
+    fn push_str(&mut self, string: String) {
         self.push_bytes(string.as_bytes())
     }
 
-    pub(crate) fn ser_number(&mut self, number: impl Into<i64>) -> () {
+    pub(crate) fn ser_number(&mut self, number: impl Display) {
         self.container.push(b'i');
-        self.push_str(number.into().to_string());
+        self.push_str(number.to_string());
         self.container.push(b'e');
     }
 
-    pub(crate) fn ser_bytes(&mut self, bytes: &[u8]) -> () {
+    pub(crate) fn ser_bytes(&mut self, bytes: &[u8]) {
         self.push_str(bytes.len().to_string());
         self.container.push(b':');
         self.push_bytes(bytes);
     }
-    pub(crate) fn ser_string(&mut self, string: &str) -> () {
+    pub(crate) fn ser_string(&mut self, string: &str) {
         self.ser_bytes(string.as_bytes())
     }
 }
 
+impl Default for Serializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bencode dictionary. With [`MapOrder::Sort`] the entries are buffered until
+/// `end`, because the format requires keys to be sorted by their raw bytes,
+/// while serde hands them out in declaration (or iteration) order.
+pub struct Dictionary<'a> {
+    ser: &'a mut Serializer,
+    order: MapOrder,
+    // Whether entries whose value is `None` are left out, as struct fields are.
+    omit_none: bool,
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    // Key waiting for its value when sorting, the last written key when verifying.
+    key: Option<Vec<u8>>,
+}
+
+impl<'a> Dictionary<'a> {
+    fn new(ser: &'a mut Serializer, order: MapOrder, len: usize, omit_none: bool) -> Self {
+        if order == MapOrder::Verify {
+            ser.container.push(b'd');
+        }
+
+        Self {
+            ser,
+            order,
+            omit_none,
+            entries: Vec::with_capacity(if order == MapOrder::Sort { len } else { 0 }),
+            key: None,
+        }
+    }
+
+    fn push_key(&mut self, key: Vec<u8>) -> Result<()> {
+        if self.order == MapOrder::Verify {
+            match self.key.as_ref().map(|last| key.cmp(last)) {
+                Some(Ordering::Less) => return Err(ErrorKind::UnsortedKey(key).into()),
+                Some(Ordering::Equal) => return Err(ErrorKind::DuplicateKey(key).into()),
+                // Written along with the value, which may still be omitted.
+                _ => {}
+            }
+        }
+
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn push_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.order {
+            MapOrder::Sort => {
+                let key = self
+                    .key
+                    .take()
+                    .expect("serialize_value called before serialize_key");
+
+                if let Some(value) = self.serialize_value(value)? {
+                    self.entries.push((key, value));
+                }
+                Ok(())
+            }
+            MapOrder::Verify => {
+                let key = self
+                    .key
+                    .as_ref()
+                    .expect("serialize_value called before serialize_key");
+                self.ser.ser_bytes(key);
+                value.serialize(&mut *self.ser)
+            }
+        }
+    }
+
+    fn push_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if let Some(value) = self.serialize_value(value)? {
+            self.entries.push((key.as_bytes().to_vec(), value));
+        }
+        Ok(())
+    }
+
+    /// Serializes `value` on its own, `None` if it is omitted.
+    fn serialize_value<T>(&self, value: &T) -> Result<Option<Vec<u8>>>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut value_ser = self.ser.child();
+        value_ser.omit_none = self.omit_none;
+        value.serialize(&mut value_ser)?;
+
+        Ok((!value_ser.none_omitted).then_some(value_ser.container))
+    }
+
+    fn finish(mut self) -> Result<&'a mut Serializer> {
+        if self.order == MapOrder::Sort {
+            self.entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            if let Some(pair) = self.entries.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                return Err(ErrorKind::DuplicateKey(pair[0].0.clone()).into());
+            }
+
+            self.ser.container.push(b'd');
+            for (key, value) in self.entries {
+                self.ser.ser_bytes(&key);
+                self.ser.push_bytes(&value);
+            }
+        }
+        self.ser.container.push(b'e');
+
+        Ok(self.ser)
+    }
+}
+
+/// Struct variant `{ variant: { fields } }`: the outer single-key dictionary
+/// is opened by `serialize_struct_variant` and closed after the inner one.
+pub struct StructVariant<'a>(Dictionary<'a>);
+
 impl<'a> ser::Serializer for &'a mut Serializer {
     type Ok = ();
 
@@ -56,122 +247,194 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 
     type SerializeTupleVariant = Self;
 
-    type SerializeMap = Self;
+    type SerializeMap = Dictionary<'a>;
 
-    type SerializeStruct = Self;
+    type SerializeStruct = Dictionary<'a>;
 
-    type SerializeStructVariant = Self;
+    type SerializeStructVariant = StructVariant<'a>;
 
-    fn serialize_bool(self, _v: bool) -> Result<Self::Ok> {
-        Err(Error::TypeNotSupported)
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
+        match self.options.bool_encoding {
+            BoolEncoding::Unsupported => Err(ErrorKind::TypeNotSupported.into()),
+            BoolEncoding::Integer => {
+                self.ser_number(u8::from(v));
+                Ok(())
+            }
+        }
     }
 
     fn serialize_i8(self, v: i8) -> Result<Self::Ok> {
-        Ok(self.ser_number(v))
+        self.ser_number(v);
+        Ok(())
     }
 
     fn serialize_i16(self, v: i16) -> Result<Self::Ok> {
-        Ok(self.ser_number(v))
+        self.ser_number(v);
+        Ok(())
     }
 
     fn serialize_i32(self, v: i32) -> Result<Self::Ok> {
-        Ok(self.ser_number(v))
+        self.ser_number(v);
+        Ok(())
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
-        Ok(self.ser_number(v))
+        self.ser_number(v);
+        Ok(())
     }
 
     fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
-        Ok(self.ser_number(v))
+        self.ser_number(v);
+        Ok(())
     }
 
     fn serialize_u16(self, v: u16) -> Result<Self::Ok> {
-        Ok(self.ser_number(v))
+        self.ser_number(v);
+        Ok(())
     }
 
     fn serialize_u32(self, v: u32) -> Result<Self::Ok> {
-        Ok(self.ser_number(v))
+        self.ser_number(v);
+        Ok(())
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
-        // FIXME: Error::Syntax????
-        let v: i64 = v.try_into().map_err(|_| Error::Syntax)?;
+        self.ser_number(v);
+        Ok(())
+    }
 
-        Ok(self.ser_number(v))
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok> {
+        self.ser_number(v);
+        Ok(())
     }
 
-    fn serialize_f32(self, _v: f32) -> Result<Self::Ok> {
-        Err(Error::TypeNotSupported)
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok> {
+        self.ser_number(v);
+        Ok(())
     }
 
-    fn serialize_f64(self, _v: f64) -> Result<Self::Ok> {
-        Err(Error::TypeNotSupported)
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
+        match self.options.float_encoding {
+            FloatEncoding::Unsupported => return Err(ErrorKind::TypeNotSupported.into()),
+            FloatEncoding::Decimal => self.ser_string(&format!("{v:?}")),
+            FloatEncoding::Binary => self.ser_bytes(&v.to_be_bytes()),
+        }
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
+        match self.options.float_encoding {
+            FloatEncoding::Unsupported => return Err(ErrorKind::TypeNotSupported.into()),
+            FloatEncoding::Decimal => self.ser_string(&format!("{v:?}")),
+            FloatEncoding::Binary => self.ser_bytes(&v.to_be_bytes()),
+        }
+        Ok(())
     }
 
-    fn serialize_char(self, _v: char) -> Result<Self::Ok> {
-        // TODO: maybe allow one-symbol string
-        Err(Error::TypeNotSupported)
+    fn serialize_char(self, v: char) -> Result<Self::Ok> {
+        self.ser_string(v.encode_utf8(&mut [0; 4]));
+        Ok(())
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok> {
-        Ok(self.ser_string(v))
+        self.ser_string(v);
+        Ok(())
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
-        Ok(self.ser_bytes(v))
+        self.ser_bytes(v);
+        Ok(())
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
-        // TODO: think about it
-        Ok(())
+        if self.omit_none && self.container.is_empty() {
+            self.none_omitted = true;
+            return Ok(());
+        }
+
+        match self.options.option_encoding {
+            OptionEncoding::Bare => Err(ErrorKind::NoneNotSupported.into()),
+            OptionEncoding::List => {
+                self.push_bytes(b"le");
+                Ok(())
+            }
+        }
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
-        value.serialize(self)
+        // Only the outermost `None` of a field can be omitted.
+        self.omit_none = false;
+
+        match self.options.option_encoding {
+            OptionEncoding::Bare => value.serialize(self),
+            OptionEncoding::List => {
+                self.container.push(b'l');
+                value.serialize(&mut *self)?;
+                self.container.push(b'e');
+                Ok(())
+            }
+        }
     }
 
+    /// Bencode has no null, so `()` is written as an empty list, the same
+    /// way an empty tuple is.
     fn serialize_unit(self) -> Result<Self::Ok> {
-        todo!()
+        self.push_bytes(b"le");
+        Ok(())
     }
 
-    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok> {
-        todo!()
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        self.serialize_unit()
     }
 
     fn serialize_unit_variant(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok> {
-        todo!()
+        self.ser_string(variant);
+        Ok(())
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(self, name: &'static str, value: &T) -> Result<Self::Ok>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
-        todo!()
+        if name == number::TOKEN {
+            // Digits of a `Number` too big for any serde primitive.
+            let digits = value.serialize(MapKeySerializer)?;
+
+            self.container.push(b'i');
+            self.push_bytes(&digits);
+            self.container.push(b'e');
+            return Ok(());
+        }
+
+        value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
-        todo!()
+        self.container.push(b'd');
+        self.ser_string(variant);
+        value.serialize(&mut *self)?;
+        self.container.push(b'e');
+        Ok(())
     }
 
-    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
         self.container.push(b'l');
         Ok(self)
     }
@@ -182,50 +445,71 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 
     fn serialize_tuple_struct(
         self,
-        name: &'static str,
+        _name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        todo!()
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_variant(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
-        len: usize,
+        _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        todo!()
+        self.container.push(b'd');
+        self.ser_string(variant);
+        self.container.push(b'l');
+        Ok(self)
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
-        self.container.push(b'd');
-        Ok(self)
+        let order = match len {
+            Some(_) => self.options.map_order,
+            None => MapOrder::Sort,
+        };
+        Ok(Dictionary::new(self, order, len.unwrap_or(0), false))
     }
 
-    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
-        todo!()
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        Ok(Dictionary::new(self, MapOrder::Sort, len, true))
     }
 
     fn serialize_struct_variant(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        todo!()
+        self.container.push(b'd');
+        self.ser_string(variant);
+        Ok(StructVariant(Dictionary::new(
+            self,
+            MapOrder::Sort,
+            len,
+            true,
+        )))
+    }
+
+    /// Bencode is a binary format, so types pick their compact form: an
+    /// `IpAddr` is `d2:V4li127ei0ei0ei1eee` rather than `9:127.0.0.1`, and a
+    /// `SocketAddr` wraps a list of the address and the port the same way.
+    /// `Duration` and `SystemTime` are dictionaries of seconds and nanoseconds.
+    fn is_human_readable(&self) -> bool {
+        false
     }
 }
 
-impl<'a> ser::SerializeSeq for &'a mut Serializer {
+impl ser::SerializeSeq for &mut Serializer {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(&mut **self)
     }
@@ -236,14 +520,14 @@ impl<'a> ser::SerializeSeq for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeTuple for &'a mut Serializer {
+impl ser::SerializeTuple for &mut Serializer {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(&mut **self)
     }
@@ -254,103 +538,468 @@ impl<'a> ser::SerializeTuple for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
+impl ser::SerializeTupleStruct for &mut Serializer {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        value.serialize(&mut **self)
     }
 
     fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
-        todo!()
+        self.container.push(b'e');
+        Ok(())
     }
 }
 
-impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
+impl ser::SerializeTupleVariant for &mut Serializer {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        value.serialize(&mut **self)
     }
 
     fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
-        todo!()
+        self.push_bytes(b"ee");
+        Ok(())
     }
 }
 
-impl<'a> ser::SerializeMap for &'a mut Serializer {
+impl ser::SerializeMap for Dictionary<'_> {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_key<T>(&mut self, key: &T) -> std::result::Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        key.serialize(&mut **self)
+        self.push_key(key.serialize(MapKeySerializer)?)
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_value<T>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        self.push_value(value)
     }
 
     fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
-        self.container.push(b'e');
+        self.finish()?;
         Ok(())
     }
 }
 
-impl<'a> ser::SerializeStruct for &'a mut Serializer {
+impl ser::SerializeStruct for Dictionary<'_> {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(
+    fn serialize_field<T>(
         &mut self,
         key: &'static str,
         value: &T,
     ) -> std::result::Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        self.push_field(key, value)
     }
 
     fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
-        todo!()
+        self.finish()?;
+        Ok(())
     }
 }
 
-impl<'a> ser::SerializeStructVariant for &'a mut Serializer {
+impl ser::SerializeStructVariant for StructVariant<'_> {
     type Ok = ();
 
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(
+    fn serialize_field<T>(
         &mut self,
         key: &'static str,
         value: &T,
     ) -> std::result::Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        self.0.push_field(key, value)
     }
 
     fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
-        todo!()
+        self.0.finish()?.container.push(b'e');
+        Ok(())
+    }
+}
+
+/// Dictionary keys are byte strings, so a key serializes to its raw bytes.
+/// Integers become their decimal representation, as in `d2:10i1ee`, and
+/// sequences of `u8` such as `Vec<u8>` or `[u8; 20]` their elements.
+struct MapKeySerializer;
+
+impl ser::Serializer for MapKeySerializer {
+    type Ok = Vec<u8>;
+
+    type Error = Error;
+
+    type SerializeSeq = MapKeyBytes;
+
+    type SerializeTuple = MapKeyBytes;
+
+    type SerializeTupleStruct = Impossible<Vec<u8>, Error>;
+
+    type SerializeTupleVariant = Impossible<Vec<u8>, Error>;
+
+    type SerializeMap = Impossible<Vec<u8>, Error>;
+
+    type SerializeStruct = Impossible<Vec<u8>, Error>;
+
+    type SerializeStructVariant = Impossible<Vec<u8>, Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok> {
+        Ok(v.as_bytes().to_vec())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
+        Ok(v.to_vec())
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok> {
+        Ok(variant.as_bytes().to_vec())
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(MapKeyBytes(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        Ok(MapKeyBytes(Vec::with_capacity(len)))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+}
+
+/// Key written from a sequence of `u8`, one byte per element.
+struct MapKeyBytes(Vec<u8>);
+
+impl ser::SerializeSeq for MapKeyBytes {
+    type Ok = Vec<u8>;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.push(value.serialize(MapKeyByte)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        Ok(self.0)
+    }
+}
+
+impl ser::SerializeTuple for MapKeyBytes {
+    type Ok = Vec<u8>;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+macro_rules! reject_key_byte {
+    ($($method:ident($ty:ty)),*) => {
+        $(
+            fn $method(self, _v: $ty) -> Result<Self::Ok> {
+                Err(ErrorKind::KeyMustBeString.into())
+            }
+        )*
+    };
+}
+
+/// Element of a [`MapKeyBytes`], which has to be a `u8`.
+struct MapKeyByte;
+
+impl ser::Serializer for MapKeyByte {
+    type Ok = u8;
+
+    type Error = Error;
+
+    type SerializeSeq = Impossible<u8, Error>;
+
+    type SerializeTuple = Impossible<u8, Error>;
+
+    type SerializeTupleStruct = Impossible<u8, Error>;
+
+    type SerializeTupleVariant = Impossible<u8, Error>;
+
+    type SerializeMap = Impossible<u8, Error>;
+
+    type SerializeStruct = Impossible<u8, Error>;
+
+    type SerializeStructVariant = Impossible<u8, Error>;
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
+        Ok(v)
+    }
+
+    reject_key_byte! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_unit_struct(&'static str)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(ErrorKind::KeyMustBeString.into())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(ErrorKind::KeyMustBeString.into())
     }
 }
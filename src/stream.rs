@@ -1,50 +1,197 @@
+//! Where the [`Deserializer`](crate::Deserializer) reads its input from: a
+//! borrowed slice, or anything implementing [`std::io::Read`].
+
 type Unit = u8;
 type UnitContainer<'a> = &'a [Unit];
 
-use crate::{Error, Result};
+use std::io::{self, BufRead, BufReader, Read as _};
+use std::ops::Deref;
+
+use crate::{Error, ErrorKind, Result};
+
+/// Source of input for the [`Deserializer`](crate::Deserializer). It can't be
+/// implemented outside of this crate.
+pub trait Input<'de>: private::Sealed {
+    /// Next byte without consuming it, `None` at the end of the input.
+    #[doc(hidden)]
+    fn peek(&mut self) -> Result<Option<Unit>>;
+
+    /// Consumes the byte returned by the last [`Input::peek`].
+    #[doc(hidden)]
+    fn discard(&mut self);
+
+    /// Offset of the next byte from the start of the input.
+    #[doc(hidden)]
+    fn position(&self) -> usize;
+
+    /// Consumes the next `n` bytes, borrowing them from the input when it
+    /// lives long enough and copying them into `scratch` otherwise.
+    #[doc(hidden)]
+    fn take<'s>(&'s mut self, n: usize, scratch: &'s mut Vec<Unit>) -> Result<Reference<'de, 's>>;
+
+    /// Bytes that can be looked at without consuming them, at least one
+    /// unless the input is over. Only used to describe errors.
+    #[doc(hidden)]
+    fn lookahead(&mut self) -> Result<UnitContainer<'_>>;
+
+    #[doc(hidden)]
+    fn try_peek(&mut self) -> Result<Unit> {
+        match self.peek()? {
+            Some(unit) => Ok(unit),
+            None => Err(ErrorKind::Eof { needed: 1 }.at(self.position())),
+        }
+    }
+
+    #[doc(hidden)]
+    fn try_next(&mut self) -> Result<Unit> {
+        let v = self.try_peek()?;
+        self.discard();
+
+        Ok(v)
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for super::BinaryStream<'_> {}
+    impl<R> Sealed for super::ReaderStream<R> {}
+}
+
+/// Bytes read by [`Input::take`], borrowed from the input for its whole
+/// lifetime `'de` or only until the next read.
+pub enum Reference<'de, 's> {
+    Borrowed(&'de [Unit]),
+    Copied(&'s [Unit]),
+}
+
+impl<'de: 's, 's> Reference<'de, 's> {
+    /// The bytes, for as long as the shorter of both lifetimes.
+    pub(crate) fn into_slice(self) -> &'s [Unit] {
+        match self {
+            Reference::Borrowed(bytes) => bytes,
+            Reference::Copied(bytes) => bytes,
+        }
+    }
+}
+
+impl Deref for Reference<'_, '_> {
+    type Target = [Unit];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Reference::Borrowed(bytes) => bytes,
+            Reference::Copied(bytes) => bytes,
+        }
+    }
+}
 
+/// Input borrowed from a slice, which values can borrow from in turn.
 pub struct BinaryStream<'s> {
     data: UnitContainer<'s>,
     p: usize,
 }
 
 impl<'s> BinaryStream<'s> {
-    pub fn new(data: UnitContainer<'s>) -> Self {
+    pub(crate) fn new(data: UnitContainer<'s>) -> Self {
         Self { data, p: 0 }
     }
 
-    pub fn try_peek(&self) -> Result<Unit> {
-        if self.p >= self.data.len() {
-            return Err(Error::Eof);
-        }
+    pub(crate) fn remainder(&self) -> UnitContainer<'s> {
+        &self.data[self.p..]
+    }
+}
 
-        Ok(self.data[self.p])
+impl<'s> Input<'s> for BinaryStream<'s> {
+    fn peek(&mut self) -> Result<Option<Unit>> {
+        Ok(self.data.get(self.p).copied())
     }
 
-    pub fn try_next(&mut self) -> Result<Unit> {
-        let v = self.try_peek()?;
+    fn discard(&mut self) {
         self.p += 1;
+    }
 
-        Ok(v)
+    fn position(&self) -> usize {
+        self.p
     }
 
-    pub fn try_take(&mut self, n: usize) -> Result<&'s [Unit]> {
-        let new_p = self.p + n;
+    fn take<'a>(&'a mut self, n: usize, _scratch: &'a mut Vec<Unit>) -> Result<Reference<'s, 'a>> {
+        let new_p = self.p.saturating_add(n);
         if new_p > self.data.len() {
-            return Err(Error::Eof);
+            let needed = new_p - self.data.len();
+            return Err(ErrorKind::Eof { needed }.at(self.data.len()));
         }
 
         let slice = &self.data[self.p..new_p];
         self.p = new_p;
 
-        Ok(slice)
+        Ok(Reference::Borrowed(slice))
+    }
+
+    fn lookahead(&mut self) -> Result<UnitContainer<'_>> {
+        Ok(self.remainder())
+    }
+}
+
+/// Input read from an [`io::Read`], buffered internally. Values are copied
+/// out of it, so only owned types can be deserialized.
+pub struct ReaderStream<R> {
+    reader: BufReader<R>,
+    p: usize,
+}
+
+impl<R: io::Read> ReaderStream<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            p: 0,
+        }
+    }
+
+    fn io_error(&self, err: io::Error) -> Error {
+        ErrorKind::Io(err).at(self.p)
     }
 }
 
-impl<'s> Iterator for &mut BinaryStream<'s> {
-    type Item = Unit;
+impl<'de, R: io::Read> Input<'de> for ReaderStream<R> {
+    fn peek(&mut self) -> Result<Option<Unit>> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok(buf) => return Ok(buf.first().copied()),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(self.io_error(err)),
+            }
+        }
+    }
+
+    fn discard(&mut self) {
+        self.reader.consume(1);
+        self.p += 1;
+    }
+
+    fn position(&self) -> usize {
+        self.p
+    }
+
+    /// The length comes from the input, so `scratch` grows with what is
+    /// actually read instead of being allocated up front.
+    fn take<'s>(&'s mut self, n: usize, scratch: &'s mut Vec<Unit>) -> Result<Reference<'de, 's>> {
+        scratch.clear();
+        let read = (&mut self.reader).take(n as u64).read_to_end(scratch);
+        self.p += scratch.len();
+        read.map_err(|err| self.io_error(err))?;
+
+        if scratch.len() < n {
+            let needed = n - scratch.len();
+            return Err(ErrorKind::Eof { needed }.at(self.p));
+        }
+
+        Ok(Reference::Copied(scratch))
+    }
+
+    fn lookahead(&mut self) -> Result<UnitContainer<'_>> {
+        self.peek()?;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.try_next().ok()
+        Ok(self.reader.buffer())
     }
 }
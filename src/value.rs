@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 
+use crate::number;
 use crate::types;
 use serde::{de, ser};
 
@@ -22,17 +23,37 @@ impl<'de> de::Deserialize<'de> for Value {
             type Value = Value;
 
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                write!(formatter, "valid barcode")
+                write!(formatter, "any bencode value")
             }
 
             fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
-                Ok(Value::Number(v))
+                Ok(Value::Number(v.into()))
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(Value::Number(v.into()))
+            }
+
+            fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E> {
+                Ok(Value::Number(v.into()))
+            }
+
+            fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E> {
+                Ok(Value::Number(v.into()))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(Value::String(v.to_owned()))
             }
 
             fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
                 Ok(Value::String(v))
             }
 
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+                Ok(Value::Bytes(v.to_vec()))
+            }
+
             fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
                 Ok(Value::Bytes(v))
             }
@@ -56,6 +77,16 @@ impl<'de> de::Deserialize<'de> for Value {
             {
                 let mut bmap = BTreeMap::new();
 
+                match map.next_key::<String>()? {
+                    Some(key) if key == number::TOKEN => {
+                        return number::visit_digits(map).map(Value::Number)
+                    }
+                    Some(key) => {
+                        bmap.insert(key, map.next_value()?);
+                    }
+                    None => return Ok(Value::Dictionary(bmap)),
+                }
+
                 while let Some((key, value)) = map.next_entry()? {
                     bmap.insert(key, value);
                 }
@@ -74,7 +105,7 @@ impl ser::Serialize for Value {
         S: serde::Serializer,
     {
         match self {
-            Value::Number(n) => serializer.serialize_i64(*n),
+            Value::Number(n) => n.serialize(serializer),
             Value::String(str) => serializer.serialize_str(str),
             Value::Bytes(bytes) => serializer.serialize_bytes(bytes),
             Value::List(list) => list.serialize(serializer),
@@ -0,0 +1,48 @@
+//! Repairs made to malformed input in lenient mode, see
+//! [`Deserializer::lenient`](crate::Deserializer::lenient).
+
+use std::fmt::{self, Display};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// Offset in the input of the repaired value.
+    pub offset: usize,
+    pub kind: WarningKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarningKind {
+    /// Dictionary key that sorts before the previous one.
+    UnsortedKey(Vec<u8>),
+    /// Dictionary key equal to the previous one. Maps keep the last value,
+    /// structs still fail with a duplicate field error.
+    DuplicateKey(Vec<u8>),
+    /// Integer written as a byte string of its digits, like `3:123`.
+    IntegerAsString,
+    /// Single value where a list was expected, read as a list of just it.
+    ValueAsList,
+    /// ASCII whitespace after the top-level value, ignored.
+    TrailingWhitespace,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            WarningKind::UnsortedKey(key) => write!(
+                f,
+                "dictionary key {:?} is out of order",
+                String::from_utf8_lossy(key)
+            ),
+            WarningKind::DuplicateKey(key) => write!(
+                f,
+                "duplicate dictionary key {:?}",
+                String::from_utf8_lossy(key)
+            ),
+            WarningKind::IntegerAsString => write!(f, "integer written as a byte string"),
+            WarningKind::ValueAsList => write!(f, "single value read as a list"),
+            WarningKind::TrailingWhitespace => write!(f, "trailing whitespace ignored"),
+        }?;
+
+        write!(f, " (byte {})", self.offset)
+    }
+}
@@ -0,0 +1,62 @@
+//! Modules for `#[serde(with = "...")]`, encoding single fields the way the
+//! [`Serializer`](crate::Serializer) and [`Deserializer`](crate::Deserializer)
+//! options do, also where those options are not applied (see
+//! [`encoding`](crate::encoding)).
+
+/// `bool` as `i1e` or `i0e`, like
+/// [`BoolEncoding::Integer`](crate::BoolEncoding::Integer).
+pub mod bool_integer {
+    use serde::de::{self, Deserialize, Deserializer, Unexpected};
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(value: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(u8::from(*value))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+        match i64::deserialize(deserializer)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            n => Err(de::Error::invalid_value(Unexpected::Signed(n), &"0 or 1")),
+        }
+    }
+}
+
+/// `f64` as the shortest decimal string that parses back to the same value,
+/// like [`FloatEncoding::Decimal`](crate::FloatEncoding::Decimal).
+pub mod float_decimal {
+    use std::fmt;
+
+    use serde::de::{self, Deserializer, Unexpected, Visitor};
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{value:?}"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        deserializer.deserialize_str(DecimalVisitor)
+    }
+
+    struct DecimalVisitor;
+
+    impl Visitor<'_> for DecimalVisitor {
+        type Value = f64;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a decimal float")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<f64, E> {
+            v.parse()
+                .map_err(|_| E::invalid_value(Unexpected::Str(v), &self))
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<f64, E> {
+            match std::str::from_utf8(v) {
+                Ok(v) => self.visit_str(v),
+                Err(_) => Err(E::invalid_value(Unexpected::Bytes(v), &self)),
+            }
+        }
+    }
+}
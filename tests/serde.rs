@@ -1,7 +1,120 @@
-use serde_bytes::Bytes;
-use std::collections::BTreeMap;
+use serde::de::IgnoredAny;
+use serde::{Deserialize as _, Serialize as _, Serializer as _};
+use serde_bytes::{ByteBuf, Bytes};
+use serde_derive::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read};
+use std::mem::discriminant;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use serde_bencode::Value;
+use serde_bencode::{
+    from_reader, BoolEncoding, Category, Deserializer, ErrorKind, FloatEncoding, Limit, Limits,
+    MapOrder, Number, OptionEncoding, PushParser, Serializer, Status, StreamDeserializer,
+    StringDecoding, Value, Warning, WarningKind,
+};
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct File {
+    path: Vec<String>,
+    length: u64,
+    #[serde(rename = "md5sum", skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+    #[serde(rename = "Z")]
+    upper: u8,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Event {
+    Started,
+    Stopped,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+enum Message {
+    Ping,
+    Choke(u32),
+    Have(u32, String),
+    Request { piece: u32, begin: u32 },
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct NodeId {
+    id: ByteBuf,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(tag = "y")]
+enum Krpc {
+    #[serde(rename = "q")]
+    Query { t: ByteBuf, q: String, a: NodeId },
+    #[serde(rename = "r")]
+    Response { t: ByteBuf, r: NodeId },
+    #[serde(rename = "e")]
+    Error { t: ByteBuf, e: (i64, String) },
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(tag = "t", content = "c")]
+enum Adjacent {
+    Empty,
+    Count(u32),
+    Pair(ByteBuf, i64),
+    Named { id: ByteBuf },
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Info<'a> {
+    name: &'a str,
+    #[serde(borrow)]
+    pieces: &'a Bytes,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, Value>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Flattened<'a> {
+    length: u64,
+    #[serde(borrow, flatten)]
+    hash: Hash<'a>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Hash<'a> {
+    md5sum: &'a str,
+    #[serde(borrow)]
+    sha1: &'a Bytes,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+enum Peers<'a> {
+    Compact(#[serde(borrow)] &'a Bytes),
+    Dictionary(Vec<Peer<'a>>),
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Peer<'a> {
+    ip: &'a str,
+    port: u16,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Announce {
+    port: u16,
+    event: Option<String>,
+    left: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct InfoHash(ByteBuf);
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Block(u32, u32, ByteBuf);
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct KeepAlive;
 
 macro_rules! de_tests {
     ($($f:ident, $t:ty, $bencode:literal, $typed:expr),*) => {
@@ -42,6 +155,9 @@ macro_rules! tests {
 
 tests! {
     test_number: i64 => (b"i-114e" == -114);
+    test_u64_max: u64 => (b"i18446744073709551615e" == u64::MAX);
+    test_i128_min: i128 => (b"i-170141183460469231731687303715884105728e" == i128::MIN);
+    test_u128_max: u128 => (b"i340282366920938463463374607431768211455e" == u128::MAX);
     test_string: String => (b"3:foo" == "foo".to_string());
     test_empty_list: Vec<String> => (b"le" == Vec::new());
     test_list: Vec<String> => (b"l4:spam4:eggse" == vec!["spam".to_string(), "eggs".to_string()]);
@@ -63,8 +179,87 @@ tests! {
     test_bytes: &Bytes => (b"4:asdf" == Bytes::new(b"asdf"));
     test_bytes_list: Vec<&Bytes> => (b"l4:teste" == vec![Bytes::new(b"test")]);
     test_borrow_str: &str => (b"4:meta" == "meta");
+    test_struct: File => (b"d1:Zi0e6:lengthi5e6:md5sum2:ff4:pathl1:a1:bee" == File {
+        path: vec!["a".to_string(), "b".to_string()],
+        length: 5,
+        checksum: Some("ff".to_string()),
+        upper: 0,
+    });
+    test_struct_skipped_field: File => (b"d1:Zi1e6:lengthi0e4:pathlee" == File {
+        path: Vec::new(),
+        length: 0,
+        checksum: None,
+        upper: 1,
+    });
+    test_unit_variant: Message => (b"4:Ping" == Message::Ping);
+    test_newtype_variant: Message => (b"d5:Chokei3ee" == Message::Choke(3));
+    test_tuple_variant: Message => (b"d4:Haveli7e2:okee" == Message::Have(7, "ok".to_string()));
+    test_struct_variant: Message => (b"d7:Requestd5:begini16e5:piecei2eee" == Message::Request { piece: 2, begin: 16 });
+    test_variant_list: Vec<Message> => (b"l4:Pingd5:Chokei0eee" == vec![Message::Ping, Message::Choke(0)]);
+    test_internally_tagged_query: Krpc => (b"d1:ad2:id2:\xff\x00e1:q4:ping1:t2:aa1:y1:qe" == Krpc::Query {
+        t: ByteBuf::from(b"aa".to_vec()),
+        q: "ping".to_string(),
+        a: NodeId { id: ByteBuf::from(vec![0xff, 0x00]) },
+    });
+    test_internally_tagged_response: Krpc => (b"d1:rd2:id1:\x80e1:t1:\xfe1:y1:re" == Krpc::Response {
+        t: ByteBuf::from(vec![0xfe]),
+        r: NodeId { id: ByteBuf::from(vec![0x80]) },
+    });
+    test_internally_tagged_error: Krpc => (b"d1:eli201e7:Generice1:t2:aa1:y1:ee" == Krpc::Error {
+        t: ByteBuf::from(b"aa".to_vec()),
+        e: (201, "Generic".to_string()),
+    });
+    test_adjacently_tagged_unit: Adjacent => (b"d1:t5:Emptye" == Adjacent::Empty);
+    test_adjacently_tagged_newtype: Adjacent => (b"d1:ci42e1:t5:Counte" == Adjacent::Count(42));
+    test_adjacently_tagged_tuple: Adjacent => (b"d1:cl1:\xffi-3ee1:t4:Paire" == Adjacent::Pair(ByteBuf::from(vec![0xff]), -3));
+    test_adjacently_tagged_struct: Adjacent => (b"d1:cd2:id1:\xc3e1:t5:Namede" == Adjacent::Named { id: ByteBuf::from(vec![0xc3]) });
+    test_unit: () => (b"le" == ());
+    test_unit_struct: KeepAlive => (b"le" == KeepAlive);
+    test_unit_in_list: Vec<()> => (b"llelee" == vec![(), ()]);
+    test_newtype_struct: InfoHash => (b"2:\x01\x02" == InfoHash(ByteBuf::from(vec![1, 2])));
+    test_newtype_struct_key: BTreeMap<InfoHash, u8> => (b"d1:\xffi1ee" == {
+        let mut map = BTreeMap::new();
+        map.insert(InfoHash(ByteBuf::from(vec![0xff])), 1);
+        map
+    });
+    test_tuple_struct: Block => (b"li1ei16384e3:abce" == Block(1, 16384, ByteBuf::from(b"abc".to_vec())));
+    test_none_field_omitted: Announce => (b"d4:lefti0e4:porti80ee" == Announce { port: 80, event: None, left: Some(0) });
+    test_some_fields: Announce => (b"d5:event7:started4:lefti1e4:porti80ee" == Announce {
+        port: 80,
+        event: Some("started".to_string()),
+        left: Some(1),
+    });
+    test_some_in_list: Vec<Option<u8>> => (b"li1ei2ee" == vec![Some(1), Some(2)]);
+    test_char: char => (b"1:x" == 'x');
+    test_multibyte_char: char => (b"3:\xe2\x82\xac" == '€');
+    test_integer_keys: BTreeMap<u32, String> => (b"d2:101:b1:91:ae" == BTreeMap::from([(9, "a".to_string()), (10, "b".to_string())]));
+    test_negative_integer_keys: BTreeMap<i64, u8> => (b"d2:-1i1e1:0i0ee" == BTreeMap::from([(-1, 1), (0, 0)]));
+    test_byte_string_keys: BTreeMap<ByteBuf, u8> => (b"d1:\x00i0e1:\xffi1ee" == BTreeMap::from([
+        (ByteBuf::from(vec![0x00]), 0),
+        (ByteBuf::from(vec![0xff]), 1),
+    ]));
+    test_char_keys: BTreeMap<char, u8> => (b"d1:ai1e1:bi2ee" == BTreeMap::from([('a', 1), ('b', 2)]));
+    test_enum_keys: BTreeMap<Event, u8> => (b"d7:Startedi1e7:Stoppedi2ee" == BTreeMap::from([(Event::Started, 1), (Event::Stopped, 2)]));
+    test_ipv4: Ipv4Addr => (b"li127ei0ei0ei1ee" == Ipv4Addr::LOCALHOST);
+    test_ipv6: Ipv6Addr => (b"li0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei1ee" == Ipv6Addr::LOCALHOST);
+    test_ip_v4: IpAddr => (b"d2:V4li10ei0ei0ei2eee" == IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+    test_ip_v6: IpAddr => (b"d2:V6li32ei1ei13ei184ei0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei1eee" == "2001:db8::1".parse::<IpAddr>().unwrap());
+    test_socket_v4: SocketAddr => (b"d2:V4lli127ei0ei0ei1eei6881eee" == "127.0.0.1:6881".parse::<SocketAddr>().unwrap());
+    test_socket_v6: SocketAddr => (b"d2:V6lli0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei0ei1eei51413eee" == "[::1]:51413".parse::<SocketAddr>().unwrap());
+    test_peer_list: Vec<SocketAddr> => (b"ld2:V4lli1ei2ei3ei4eei80eeee" == vec!["1.2.3.4:80".parse().unwrap()]);
+    test_duration: Duration => (b"d5:nanosi500e4:secsi90ee" == Duration::new(90, 500));
+    test_system_time: SystemTime => (b"d17:nanos_since_epochi7e16:secs_since_epochi1697466120ee" == UNIX_EPOCH + Duration::new(1697466120, 7));
 
-    test_dyn_number: _ => (b"i4e" == Value::Number(4));
+    test_dyn_number: _ => (b"i4e" == Value::Number(4.into()));
+    test_dyn_u64: _ => (b"i18446744073709551615e" == Value::Number(u64::MAX.into()));
+    test_dyn_u128: _ => (b"i340282366920938463463374607431768211455e" == Value::Number(u128::MAX.into()));
+    test_dyn_big_number: _ => (b"i-1234567890123456789012345678901234567890123456789e" == Value::Number(
+        "-1234567890123456789012345678901234567890123456789".parse().unwrap()
+    ));
+    test_dyn_big_number_in_list: _ => (b"li99999999999999999999999999999999999999999999ei1ee" == Value::List(vec![
+        Value::Number("99999999999999999999999999999999999999999999".parse().unwrap()),
+        Value::Number(1.into()),
+    ]));
     test_dyn_string: _ => (b"4:test" == Value::String("test".to_string()));
     test_dyn_bytes: _ => (b"4:l\xFFlw" == Value::Bytes(b"l\xFFlw".to_vec()));
     test_dyn_list: _ => (b"l3:foo3:bare" == Value::List(vec![Value::String("foo".to_string()), Value::String("bar".to_string())]));
@@ -77,3 +272,1412 @@ tests! {
         Value::Dictionary(map)
     })
 }
+
+#[test]
+fn test_unknown_variant() {
+    let err = serde_bencode::from_binary::<Message>(b"d4:Bitei1ee").unwrap_err();
+    assert!(err.to_string().starts_with("unknown variant `Bite`"));
+
+    let err = serde_bencode::from_binary::<Message>(b"5:Unite").unwrap_err();
+    assert!(err.to_string().starts_with("unknown variant `Unite`"));
+}
+
+#[test]
+fn test_variant_with_extra_key() {
+    assert!(serde_bencode::from_binary::<Message>(b"d5:Chokei3e4:Pingi0ee").is_err());
+}
+
+#[test]
+fn test_internally_tagged_unsorted_input() {
+    let krpc = serde_bencode::from_binary::<Krpc>(b"d1:y1:r1:t1:\xfe1:rd2:id1:\x80ee").unwrap();
+
+    assert_eq!(
+        krpc,
+        Krpc::Response {
+            t: ByteBuf::from(vec![0xfe]),
+            r: NodeId {
+                id: ByteBuf::from(vec![0x80])
+            },
+        }
+    );
+}
+
+#[test]
+fn test_flatten_unknown_keys() {
+    let info =
+        serde_bencode::from_binary::<Info>(b"d6:lengthi7e4:name1:a6:pieces2:\xff\x007:privatei1ee")
+            .unwrap();
+
+    let mut unknown = BTreeMap::new();
+    unknown.insert("length".to_string(), Value::Number(7.into()));
+    unknown.insert("private".to_string(), Value::Number(1.into()));
+    assert_eq!(
+        info,
+        Info {
+            name: "a",
+            pieces: Bytes::new(b"\xff\x00"),
+            unknown,
+        }
+    );
+}
+
+#[test]
+fn test_flatten_borrowed_fields() {
+    let flattened =
+        serde_bencode::from_binary::<Flattened>(b"d6:lengthi3e6:md5sum2:ab4:sha12:\x80\x81e")
+            .unwrap();
+
+    assert_eq!(
+        flattened,
+        Flattened {
+            length: 3,
+            hash: Hash {
+                md5sum: "ab",
+                sha1: Bytes::new(b"\x80\x81"),
+            },
+        }
+    );
+}
+
+#[test]
+fn test_untagged_borrowed_fields() {
+    let compact = serde_bencode::from_binary::<Peers>(b"6:\x7f\x00\x00\x01\x1a\xe1").unwrap();
+    assert_eq!(
+        compact,
+        Peers::Compact(Bytes::new(b"\x7f\x00\x00\x01\x1a\xe1"))
+    );
+
+    let dictionary =
+        serde_bencode::from_binary::<Peers>(b"ld2:ip9:127.0.0.14:porti6881eee").unwrap();
+    assert_eq!(
+        dictionary,
+        Peers::Dictionary(vec![Peer {
+            ip: "127.0.0.1",
+            port: 6881,
+        }])
+    );
+}
+
+#[test]
+fn test_hash_map_sorted() {
+    let map: HashMap<&str, u8> = [("two", 2), ("one", 1), ("three", 3), ("four", 4)].into();
+
+    assert_eq!(
+        serde_bencode::to_binary(&map).unwrap(),
+        b"d4:fouri4e3:onei1e5:threei3e3:twoi2ee"
+    );
+}
+
+#[test]
+fn test_map_sorted_by_raw_bytes() {
+    let map: HashMap<&Bytes, u8> = [
+        (Bytes::new(b"\xff"), 1),
+        (Bytes::new(b"a"), 2),
+        (Bytes::new(b"Z"), 3),
+    ]
+    .into();
+
+    assert_eq!(
+        serde_bencode::to_binary(&map).unwrap(),
+        b"d1:Zi3e1:ai2e1:\xffi1ee"
+    );
+}
+
+#[derive(Serialize)]
+struct Extended {
+    name: &'static str,
+    #[serde(flatten)]
+    extra: BTreeMap<&'static str, u8>,
+}
+
+#[test]
+fn test_flatten_sorted() {
+    let extended = Extended {
+        name: "n",
+        extra: [("a", 1), ("z", 2)].into(),
+    };
+
+    assert_eq!(
+        serde_bencode::to_binary(&extended).unwrap(),
+        b"d1:ai1e4:name1:n1:zi2ee"
+    );
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Outer {
+    x: u8,
+    #[serde(flatten)]
+    inner: Inner,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Inner {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    a: Option<u8>,
+    b: u8,
+}
+
+#[test]
+fn test_flatten_none_skipped() {
+    let outer = Outer {
+        x: 1,
+        inner: Inner { a: None, b: 2 },
+    };
+
+    let bencode = serde_bencode::to_binary(&outer).unwrap();
+    assert_eq!(bencode, b"d1:bi2e1:xi1ee");
+    assert_eq!(
+        serde_bencode::from_binary::<Outer>(&bencode).unwrap(),
+        outer
+    );
+
+    // Flattened fields are map entries, so `None` is only left out if asked.
+    #[derive(Serialize)]
+    struct Loose {
+        a: Option<u8>,
+    }
+    #[derive(Serialize)]
+    struct Flat {
+        #[serde(flatten)]
+        loose: Loose,
+    }
+    let flat = Flat {
+        loose: Loose { a: None },
+    };
+    assert!(matches!(
+        serde_bencode::to_binary(&flat).unwrap_err().kind(),
+        ErrorKind::NoneNotSupported
+    ));
+}
+
+#[test]
+fn test_map_duplicate_key() {
+    let extended = Extended {
+        name: "n",
+        extra: [("name", 1)].into(),
+    };
+
+    assert!(
+        matches!(serde_bencode::to_binary(&extended).unwrap_err().kind(), ErrorKind::DuplicateKey(key) if key == b"name")
+    );
+}
+
+#[test]
+fn test_map_order_verify() {
+    let sorted: BTreeMap<&str, u8> = [("a", 1), ("b", 2)].into();
+    let mut serializer = Serializer::new().map_order(MapOrder::Verify);
+    sorted.serialize(&mut serializer).unwrap();
+    assert_eq!(serializer.into_inner(), b"d1:ai1e1:bi2ee");
+
+    let unsorted = vec![("b", 2), ("a", 1)];
+    let mut serializer = Serializer::new().map_order(MapOrder::Verify);
+    let result = (&mut serializer).collect_map(unsorted);
+    assert!(matches!(result.unwrap_err().kind(), ErrorKind::UnsortedKey(key) if key == b"a"));
+
+    let duplicated = vec![("a", 1), ("a", 2)];
+    let mut serializer = Serializer::new().map_order(MapOrder::Verify);
+    let result = (&mut serializer).collect_map(duplicated);
+    assert!(matches!(result.unwrap_err().kind(), ErrorKind::DuplicateKey(key) if key == b"a"));
+
+    // Flattened fields come in declaration order, and are sorted anyway.
+    let outer = Outer {
+        x: 1,
+        inner: Inner { a: Some(3), b: 2 },
+    };
+    let mut serializer = Serializer::new().map_order(MapOrder::Verify);
+    outer.serialize(&mut serializer).unwrap();
+    assert_eq!(serializer.into_inner(), b"d1:ai3e1:bi2e1:xi1ee");
+}
+
+#[test]
+fn test_map_key_must_be_string() {
+    let map: BTreeMap<(u8, String), u8> = [((1, "a".to_string()), 1)].into();
+    assert!(matches!(
+        serde_bencode::to_binary(&map).unwrap_err().kind(),
+        ErrorKind::KeyMustBeString
+    ));
+
+    let map: BTreeMap<Vec<u16>, u8> = [(vec![1], 1)].into();
+    assert!(matches!(
+        serde_bencode::to_binary(&map).unwrap_err().kind(),
+        ErrorKind::KeyMustBeString
+    ));
+
+    assert!(matches!(
+        serde_bencode::from_binary::<BTreeMap<String, u8>>(b"di1ei2ee")
+            .unwrap_err()
+            .kind(),
+        ErrorKind::KeyMustBeString
+    ));
+    assert!(serde_bencode::from_binary::<BTreeMap<u8, u8>>(b"d1:xi2ee").is_err());
+
+    // Integer keys are written the way integers are, so that distinct keys
+    // can't read back as the same one.
+    let err = serde_bencode::from_binary::<BTreeMap<u32, u8>>(b"d1:1i1e2:01i2ee").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::LeadingZero));
+    assert_eq!(err.offset(), Some(7));
+    let err = serde_bencode::from_binary::<BTreeMap<i32, u8>>(b"d2:-0i1ee").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::NegativeZero));
+    let err = serde_bencode::from_binary::<BTreeMap<u32, u8>>(b"d2:+1i1ee").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidValue { .. }));
+}
+
+#[test]
+fn test_map_key_bytes() {
+    let map: BTreeMap<Vec<u8>, u8> = [(vec![0xff, 0], 1), (b"b".to_vec(), 2)].into();
+    let bencode = serde_bencode::to_binary(&map).unwrap();
+    assert_eq!(bencode, b"d1:bi2e2:\xff\x00i1ee");
+    assert_eq!(
+        serde_bencode::from_binary::<BTreeMap<Vec<u8>, u8>>(&bencode).unwrap(),
+        map
+    );
+
+    let map: BTreeMap<[u8; 2], u8> = [([1, 2], 3)].into();
+    let bencode = serde_bencode::to_binary(&map).unwrap();
+    assert_eq!(bencode, b"d2:\x01\x02i3ee");
+    assert_eq!(
+        serde_bencode::from_binary::<BTreeMap<[u8; 2], u8>>(&bencode).unwrap(),
+        map
+    );
+    assert!(serde_bencode::from_binary::<BTreeMap<[u8; 2], u8>>(b"d3:abci3ee").is_err());
+}
+
+#[test]
+fn test_none_not_omittable() {
+    assert!(matches!(
+        serde_bencode::to_binary(&None::<u8>).unwrap_err().kind(),
+        ErrorKind::NoneNotSupported
+    ));
+    assert!(matches!(
+        serde_bencode::to_binary(&vec![Some(1), None])
+            .unwrap_err()
+            .kind(),
+        ErrorKind::NoneNotSupported
+    ));
+    assert!(matches!(
+        serde_bencode::to_binary(&(1, None::<u8>))
+            .unwrap_err()
+            .kind(),
+        ErrorKind::NoneNotSupported
+    ));
+
+    let map: BTreeMap<&str, Option<u8>> = [("a", None)].into();
+    assert!(matches!(
+        serde_bencode::to_binary(&map).unwrap_err().kind(),
+        ErrorKind::NoneNotSupported
+    ));
+
+    // Also when the length of the map is not known up front.
+    let entries = vec![("a", Some(1u8)), ("b", None), ("c", Some(3))];
+    let mut serializer = Serializer::new();
+    let result = (&mut serializer).collect_map(entries.into_iter().filter(|_| true));
+    assert!(matches!(
+        result.unwrap_err().kind(),
+        ErrorKind::NoneNotSupported
+    ));
+}
+
+fn round_trip_with<T>(
+    value: T,
+    bencode: &[u8],
+    mut serializer: Serializer,
+    mut deserializer: Deserializer,
+) where
+    T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(serializer.into_inner(), bencode);
+
+    assert_eq!(T::deserialize(&mut deserializer).unwrap(), value);
+}
+
+/// Round-trips each value through its bencode, with the same option set on
+/// both the serializer and the deserializer.
+macro_rules! round_trip {
+    ($option:ident($encoding:expr): $($value:expr => $bencode:expr),* $(,)?) => {
+        $(
+            round_trip_with(
+                $value,
+                $bencode,
+                Serializer::new().$option($encoding),
+                Deserializer::new($bencode).$option($encoding),
+            );
+        )*
+    };
+}
+
+#[test]
+fn test_option_list_encoding() {
+    round_trip!(option_encoding(OptionEncoding::List):
+        vec![Some(1), None, Some(3)] => b"lli1eeleli3eee",
+        vec![Some(None), Some(Some(1u8))] => b"llleelli1eeee",
+        BTreeMap::from([
+            ("a".to_string(), None),
+            ("b".to_string(), Some(vec![2])),
+        ]) => b"d1:ale1:blli2eeee",
+        Announce {
+            port: 80,
+            event: None,
+            left: Some(0),
+        } => b"d4:leftli0ee4:porti80ee",
+    );
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Flags {
+    private: bool,
+    seed: bool,
+}
+
+#[test]
+fn test_bool_integer_encoding() {
+    round_trip!(bool_encoding(BoolEncoding::Integer):
+        true => b"i1e",
+        false => b"i0e",
+        Flags {
+            private: true,
+            seed: false,
+        } => b"d7:privatei1e4:seedi0ee",
+    );
+
+    let mut deserializer = Deserializer::new(b"i2e").bool_encoding(BoolEncoding::Integer);
+    assert!(matches!(
+        bool::deserialize(&mut deserializer).unwrap_err().kind(),
+        ErrorKind::InvalidBool(2)
+    ));
+}
+
+#[test]
+fn test_bool_unsupported_by_default() {
+    assert!(matches!(
+        serde_bencode::to_binary(&true).unwrap_err().kind(),
+        ErrorKind::TypeNotSupported
+    ));
+    assert!(matches!(
+        serde_bencode::from_binary::<bool>(b"i1e")
+            .unwrap_err()
+            .kind(),
+        ErrorKind::TypeNotSupported
+    ));
+}
+
+#[test]
+fn test_char_must_be_single() {
+    assert!(serde_bencode::from_binary::<char>(b"2:ab").is_err());
+    assert!(serde_bencode::from_binary::<char>(b"0:").is_err());
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Progress {
+    ratio: f64,
+    done: f32,
+}
+
+#[test]
+fn test_float_decimal_encoding() {
+    round_trip!(float_encoding(FloatEncoding::Decimal):
+        0.1f64 => b"3:0.1",
+        0.1f32 => b"3:0.1",
+        -2.5e-300f64 => b"9:-2.5e-300",
+        f64::INFINITY => b"3:inf",
+        Progress {
+            ratio: 1.0,
+            done: 0.75,
+        } => b"d4:done4:0.755:ratio3:1.0e",
+    );
+
+    let mut deserializer = Deserializer::new(b"3:abc").float_encoding(FloatEncoding::Decimal);
+    assert!(matches!(
+        f64::deserialize(&mut deserializer).unwrap_err().kind(),
+        ErrorKind::InvalidFloat
+    ));
+}
+
+#[test]
+fn test_float_binary_encoding() {
+    round_trip!(float_encoding(FloatEncoding::Binary):
+        1.5f32 => b"4:\x3f\xc0\x00\x00",
+        -2.0f64 => b"8:\xc0\x00\x00\x00\x00\x00\x00\x00",
+    );
+
+    let mut deserializer =
+        Deserializer::new(b"4:\x3f\xc0\x00\x00").float_encoding(FloatEncoding::Binary);
+    assert_eq!(f64::deserialize(&mut deserializer).unwrap(), 1.5);
+
+    let mut deserializer = Deserializer::new(b"2:ab").float_encoding(FloatEncoding::Binary);
+    assert!(matches!(
+        f32::deserialize(&mut deserializer).unwrap_err().kind(),
+        ErrorKind::InvalidFloat
+    ));
+}
+
+#[test]
+fn test_float_unsupported_by_default() {
+    assert!(matches!(
+        serde_bencode::to_binary(&1.0f64).unwrap_err().kind(),
+        ErrorKind::TypeNotSupported
+    ));
+    assert!(matches!(
+        serde_bencode::from_binary::<f32>(b"3:1.0")
+            .unwrap_err()
+            .kind(),
+        ErrorKind::TypeNotSupported
+    ));
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(tag = "y")]
+enum Tagged {
+    #[serde(rename = "q")]
+    Query {
+        ro: bool,
+        ratio: f64,
+        seq: Option<u8>,
+    },
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(tag = "y")]
+enum TaggedWith {
+    #[serde(rename = "q")]
+    Query {
+        #[serde(with = "serde_bencode::with::bool_integer")]
+        ro: bool,
+        #[serde(with = "serde_bencode::with::float_decimal")]
+        ratio: f64,
+        seq: Option<u8>,
+    },
+}
+
+#[test]
+fn test_encodings_in_tagged_enum() {
+    let query = Tagged::Query {
+        ro: true,
+        ratio: 0.5,
+        seq: Some(1),
+    };
+    let mut serializer = Serializer::new()
+        .bool_encoding(BoolEncoding::Integer)
+        .float_encoding(FloatEncoding::Decimal)
+        .option_encoding(OptionEncoding::List);
+    query.serialize(&mut serializer).unwrap();
+    let bencode = serializer.into_inner();
+    assert_eq!(bencode, b"d5:ratio3:0.52:roi1e3:seqli1ee1:y1:qe");
+
+    // Serde reads the content of a tagged enum into its own buffer first,
+    // and then decodes it without the options.
+    let mut deserializer = Deserializer::new(&bencode)
+        .bool_encoding(BoolEncoding::Integer)
+        .float_encoding(FloatEncoding::Decimal)
+        .option_encoding(OptionEncoding::List);
+    assert!(matches!(
+        Tagged::deserialize(&mut deserializer).unwrap_err().kind(),
+        ErrorKind::InvalidType { .. }
+    ));
+
+    // The `with` modules apply there too, and an omitted field is `None`.
+    let query = TaggedWith::Query {
+        ro: true,
+        ratio: 0.5,
+        seq: None,
+    };
+    let bencode = b"d5:ratio3:0.52:roi1e1:y1:qe";
+    assert_eq!(serde_bencode::to_binary(&query).unwrap(), bencode);
+    assert_eq!(
+        serde_bencode::from_binary::<TaggedWith>(bencode).unwrap(),
+        query
+    );
+    for bencode in [
+        &b"d5:ratio3:0.52:roi2e1:y1:qe"[..],
+        b"d5:ratio3:abc2:roi1e1:y1:qe",
+    ] {
+        assert!(matches!(
+            serde_bencode::from_binary::<TaggedWith>(bencode)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidValue { .. }
+        ));
+    }
+}
+
+#[test]
+fn test_integer_out_of_range() {
+    for result in [
+        serde_bencode::from_binary::<u64>(b"i18446744073709551616e"),
+        serde_bencode::from_binary::<u64>(b"i-1e"),
+        serde_bencode::from_binary::<u8>(b"i256e").map(u64::from),
+    ] {
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::IntegerOverflow { .. }
+        ));
+    }
+    assert!(matches!(
+        serde_bencode::from_binary::<&[u8]>(b"99999999999999999999999:a")
+            .unwrap_err()
+            .kind(),
+        ErrorKind::IntegerOverflow { .. }
+    ));
+}
+
+#[test]
+fn test_integer_grammar() {
+    let cases: [(&[u8], ErrorKind); 10] = [
+        (b"i05e", ErrorKind::LeadingZero),
+        (b"i00e", ErrorKind::LeadingZero),
+        (b"i-05e", ErrorKind::LeadingZero),
+        (b"i-0e", ErrorKind::NegativeZero),
+        (b"ie", ErrorKind::Syntax),
+        (b"i-e", ErrorKind::Syntax),
+        (b"i1-2e", ErrorKind::MissingTerminator),
+        (b"i12", ErrorKind::Eof { needed: 1 }),
+        (b"-1:a", ErrorKind::NegativeLength),
+        (b"03:abc", ErrorKind::LeadingZero),
+    ];
+
+    for (bencode, expected) in cases {
+        let err = serde_bencode::from_binary::<Value>(bencode).unwrap_err();
+        assert_eq!(
+            discriminant(err.kind()),
+            discriminant(&expected),
+            "{bencode:?}"
+        );
+    }
+
+    assert_eq!(serde_bencode::from_binary::<i64>(b"i0e").unwrap(), 0);
+    assert_eq!(serde_bencode::from_binary::<i64>(b"i-10e").unwrap(), -10);
+    assert_eq!(serde_bencode::from_binary::<&str>(b"0:").unwrap(), "");
+}
+
+#[test]
+fn test_trailing_data() {
+    assert!(matches!(
+        serde_bencode::from_binary::<i64>(b"i1egarbage")
+            .unwrap_err()
+            .kind(),
+        ErrorKind::TrailingData
+    ));
+    assert!(matches!(
+        serde_bencode::from_binary::<Vec<i64>>(b"li1eee")
+            .unwrap_err()
+            .kind(),
+        ErrorKind::TrailingData
+    ));
+
+    let mut deserializer = Deserializer::new(b"i1ei2e");
+    assert_eq!(i64::deserialize(&mut deserializer).unwrap(), 1);
+    assert!(matches!(
+        deserializer.end().unwrap_err().kind(),
+        ErrorKind::TrailingData
+    ));
+    assert_eq!(deserializer.remainder(), b"i2e");
+}
+
+#[test]
+fn test_prefix() {
+    let message = b"d8:msg_typei1e5:piecei0ee\x00\x01\x02";
+    let (header, piece): (BTreeMap<String, i64>, _) =
+        serde_bencode::from_binary_prefix(message).unwrap();
+    assert_eq!(header["msg_type"], 1);
+    assert_eq!(piece, b"\x00\x01\x02");
+
+    let (value, rest): (i64, _) = serde_bencode::from_binary_prefix(b"i7e").unwrap();
+    assert_eq!(value, 7);
+    assert!(rest.is_empty());
+
+    assert!(matches!(
+        serde_bencode::from_binary_prefix::<i64>(b"i7")
+            .unwrap_err()
+            .kind(),
+        ErrorKind::Eof { .. }
+    ));
+}
+
+#[test]
+fn test_canonical() {
+    let mut deserializer = Deserializer::new(b"d1:bi1e1:ai2ee").canonical(true);
+    let err = BTreeMap::<String, i64>::deserialize(&mut deserializer).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnsortedKey(key) if key == b"a"));
+    assert_eq!(err.offset(), Some(7));
+
+    let mut deserializer = Deserializer::new(b"ld1:ai1e1:ai2eee").canonical(true);
+    let err = Value::deserialize(&mut deserializer).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::DuplicateKey(key) if key == b"a"));
+    assert_eq!(err.offset(), Some(8));
+    assert_eq!(err.path(), "[0]");
+
+    // Without the check the last duplicate wins, as for any serde map.
+    let map: BTreeMap<String, i64> = serde_bencode::from_binary(b"d1:bi1e1:ai2ee").unwrap();
+    assert_eq!(map.len(), 2);
+}
+
+#[test]
+fn test_is_canonical() {
+    assert!(serde_bencode::is_canonical(b"d1:ai1e1:bld1:xi0eeee").unwrap());
+    assert!(serde_bencode::is_canonical(b"d1:a0:2:aai0ee").unwrap());
+    assert!(!serde_bencode::is_canonical(b"d1:bi1e1:ai2ee").unwrap());
+    assert!(!serde_bencode::is_canonical(b"d1:ald1:yi0e1:xi0eeee").unwrap());
+    assert!(!serde_bencode::is_canonical(b"d1:ai1e1:ai1ee").unwrap());
+    assert!(!serde_bencode::is_canonical(b"i01e").unwrap());
+    assert!(!serde_bencode::is_canonical(b"i1ei2e").unwrap());
+    assert!(!serde_bencode::is_canonical(b"d1:a").unwrap());
+
+    let nested = [vec![b'l'; 200], vec![b'e'; 200]].concat();
+    assert!(matches!(
+        serde_bencode::is_canonical(&nested).unwrap_err().kind(),
+        ErrorKind::LimitExceeded(Limit::Depth)
+    ));
+}
+
+#[test]
+fn test_depth_limit() {
+    let nested = [vec![b'l'; 100_000], vec![b'e'; 100_000]].concat();
+    assert!(matches!(
+        serde_bencode::from_binary::<Value>(&nested)
+            .unwrap_err()
+            .kind(),
+        ErrorKind::LimitExceeded(Limit::Depth)
+    ));
+
+    let limits = Limits {
+        max_depth: 2,
+        ..Limits::default()
+    };
+    let mut deserializer = Deserializer::new(b"llleee").limits(limits);
+    assert!(matches!(
+        Value::deserialize(&mut deserializer).unwrap_err().kind(),
+        ErrorKind::LimitExceeded(Limit::Depth)
+    ));
+    let mut deserializer = Deserializer::new(b"d1:ald1:xd1:yleeeee").limits(limits);
+    assert!(matches!(
+        IgnoredAny::deserialize(&mut deserializer)
+            .unwrap_err()
+            .kind(),
+        ErrorKind::LimitExceeded(Limit::Depth)
+    ));
+    let mut deserializer = Deserializer::new(b"lleli1eee").limits(limits);
+    assert!(Vec::<Vec<u8>>::deserialize(&mut deserializer).is_ok());
+}
+
+#[derive(Deserialize, Debug)]
+struct Chain(#[allow(dead_code)] Option<Box<Chain>>);
+
+#[test]
+fn test_depth_limit_option_list() {
+    let nested = vec![b'l'; 1_000_000];
+    let mut deserializer = Deserializer::new(&nested).option_encoding(OptionEncoding::List);
+    assert!(matches!(
+        Chain::deserialize(&mut deserializer).unwrap_err().kind(),
+        ErrorKind::LimitExceeded(Limit::Depth)
+    ));
+
+    let limits = Limits {
+        max_depth: 2,
+        ..Limits::default()
+    };
+    let mut deserializer = Deserializer::new(b"lllleeee")
+        .option_encoding(OptionEncoding::List)
+        .limits(limits);
+    assert!(matches!(
+        Chain::deserialize(&mut deserializer).unwrap_err().kind(),
+        ErrorKind::LimitExceeded(Limit::Depth)
+    ));
+    let mut deserializer = Deserializer::new(b"llleee")
+        .option_encoding(OptionEncoding::List)
+        .limits(limits);
+    assert!(Chain::deserialize(&mut deserializer).is_ok());
+}
+
+#[test]
+fn test_length_limits() {
+    let limits = Limits {
+        max_bytes_len: 3,
+        max_collection_len: 2,
+        max_total_bytes: 5,
+        max_integer_len: 4,
+        ..Limits::none()
+    };
+    let cases: [(&[u8], Limit); 6] = [
+        (b"999:", Limit::BytesLen),
+        (b"i-12345e", Limit::IntegerLen),
+        (b"00000:", Limit::IntegerLen),
+        (b"li1ei2ei3ee", Limit::CollectionLen),
+        (b"d1:ai1e1:bi2e1:ci3ee", Limit::CollectionLen),
+        (b"l3:abc3:defe", Limit::TotalBytes),
+    ];
+
+    for (bencode, limit) in cases {
+        let mut deserializer = Deserializer::new(bencode).limits(limits);
+        assert!(
+            matches!(Value::deserialize(&mut deserializer).unwrap_err().kind(), ErrorKind::LimitExceeded(l) if *l == limit),
+            "{bencode:?}"
+        );
+    }
+
+    for bencode in [&b"l3:abc2:dee"[..], b"i-1234e"] {
+        let mut deserializer = Deserializer::new(bencode).limits(limits);
+        assert!(Value::deserialize(&mut deserializer).is_ok());
+    }
+
+    // Digits are not buffered without bound either.
+    let endless = Read::chain(&b"i"[..], io::repeat(b'1'));
+    let mut deserializer = Deserializer::from_reader(endless).limits(limits);
+    assert!(matches!(
+        i64::deserialize(&mut deserializer).unwrap_err().kind(),
+        ErrorKind::LimitExceeded(Limit::IntegerLen)
+    ));
+}
+
+#[test]
+fn test_error_location() {
+    let files = b"d5:filesld1:Zi0e6:lengthi1e4:pathl1:aeed1:Zi0e6:length1:x4:pathl1:beeee";
+    let err = serde_bencode::from_binary::<BTreeMap<String, Vec<File>>>(files).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidType { .. }));
+    assert_eq!(err.path(), "files[1].length");
+    assert_eq!(err.offset(), Some(54));
+    assert_eq!(
+        err.to_string(),
+        "expected integer, found byte string of length 1 at `files[1].length` (byte 54)"
+    );
+
+    // Errors raised by serde itself point at the start of the value.
+    let err = serde_bencode::from_binary::<Vec<File>>(b"ld1:Zi0e6:lengthi1eee").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::Message(_)));
+    assert_eq!(err.path(), "[0]");
+    assert_eq!(err.offset(), Some(1));
+
+    let err = serde_bencode::from_binary::<Event>(b"d7:Startedi1ee").unwrap_err();
+    assert_eq!(err.offset(), Some(10));
+
+    let err = serde_bencode::from_binary::<Value>(b"li1ei2x").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::MissingTerminator));
+    assert_eq!((err.path().as_str(), err.offset()), ("[1]", Some(6)));
+
+    let err = serde_bencode::from_binary::<Value>(b"i1ei2e").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::TrailingData));
+    assert_eq!(err.offset(), Some(3));
+}
+
+#[test]
+fn test_error_category() {
+    let err = serde_bencode::from_binary::<Value>(b"20:abc").unwrap_err();
+    assert_eq!(err.classify(), Category::Eof);
+    assert!(err.is_eof());
+    assert_eq!(err.needed(), Some(17));
+
+    let err = serde_bencode::from_binary::<Value>(b"l1:a").unwrap_err();
+    assert!(err.is_eof());
+    assert_eq!(err.needed(), Some(1));
+
+    let err = serde_bencode::from_binary::<Value>(b"i01e").unwrap_err();
+    assert_eq!(err.classify(), Category::Syntax);
+    assert_eq!(err.needed(), None);
+
+    let err = serde_bencode::from_binary::<u64>(b"20:aaaaaaaaaaaaaaaaaaaa").unwrap_err();
+    assert!(err.is_data());
+    assert_eq!(
+        err.to_string(),
+        "expected integer, found byte string of length 20 (byte 0)"
+    );
+
+    let err = serde_bencode::from_binary::<u8>(b"i256e").unwrap_err();
+    assert!(err.is_data());
+    assert_eq!(
+        err.to_string(),
+        "integer 256 is out of range for u8 (byte 0)"
+    );
+
+    let err = serde_bencode::from_binary::<BTreeMap<u8, u8>>(b"d3:300i1ee").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "integer 300 is out of range for u8 (byte 1)"
+    );
+
+    // Raised by serde for a visitor, in terms of what was in the bencode.
+    let err = serde_bencode::from_binary::<Number>(b"3:abc").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected an integer, found byte string of length 3 (byte 0)"
+    );
+    let err = serde_bencode::from_binary::<Vec<u8>>(b"3:abc").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected list, found byte string of length 3 (byte 0)"
+    );
+    let err = serde_bencode::from_binary::<String>(b"i3e").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected byte string, found integer (byte 0)"
+    );
+    let err = serde_bencode::from_binary::<Value>(b"x").unwrap_err();
+    assert!(err.is_syntax());
+}
+
+#[test]
+fn test_lenient() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Torrent {
+        #[serde(rename = "announce-list")]
+        announce_list: Vec<Vec<String>>,
+        length: u64,
+    }
+
+    let bencode = b"d6:length3:12313:announce-listl1:a1:bee\n";
+    assert!(serde_bencode::from_binary::<Torrent>(bencode).is_err());
+
+    let (torrent, warnings) = serde_bencode::from_binary_lenient::<Torrent>(bencode).unwrap();
+    assert_eq!(
+        torrent,
+        Torrent {
+            announce_list: vec![vec!["a".into()], vec!["b".into()]],
+            length: 123,
+        }
+    );
+    assert_eq!(
+        warnings,
+        [
+            Warning {
+                offset: 9,
+                kind: WarningKind::IntegerAsString,
+            },
+            Warning {
+                offset: 14,
+                kind: WarningKind::UnsortedKey(b"announce-list".to_vec()),
+            },
+            Warning {
+                offset: 31,
+                kind: WarningKind::ValueAsList,
+            },
+            Warning {
+                offset: 34,
+                kind: WarningKind::ValueAsList,
+            },
+            Warning {
+                offset: 39,
+                kind: WarningKind::TrailingWhitespace,
+            },
+        ]
+    );
+    assert_eq!(
+        warnings[0].to_string(),
+        "integer written as a byte string (byte 9)"
+    );
+}
+
+#[test]
+fn test_lenient_still_rejects() {
+    let mut deserializer = Deserializer::new(b"d1:ai1e1:ai2ee").lenient(true);
+    let map = BTreeMap::<String, u8>::deserialize(&mut deserializer).unwrap();
+    assert_eq!(map["a"], 2);
+    assert_eq!(
+        deserializer.warnings(),
+        [Warning {
+            offset: 7,
+            kind: WarningKind::DuplicateKey(b"a".to_vec()),
+        }]
+    );
+
+    let mut deserializer = Deserializer::new(b"d1:bi1e1:ai2ee")
+        .lenient(true)
+        .canonical(true);
+    let err = BTreeMap::<String, u8>::deserialize(&mut deserializer).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnsortedKey(_)));
+
+    let err = serde_bencode::from_binary_lenient::<u64>(b"3:abc").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidValue { .. }));
+    let err = serde_bencode::from_binary_lenient::<u64>(b"2:+5").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidValue { .. }));
+    let err = serde_bencode::from_binary_lenient::<u64>(b"3:007").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::LeadingZero));
+    let err = serde_bencode::from_binary_lenient::<i64>(b"2:-0").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::NegativeZero));
+    let err = serde_bencode::from_binary_lenient::<u8>(b"3:256").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::IntegerOverflow { .. }));
+    assert_eq!(
+        serde_bencode::from_binary_lenient::<i64>(b"2:-5")
+            .unwrap()
+            .0,
+        -5
+    );
+    let err = serde_bencode::from_binary_lenient::<u64>(b"i1e\nx").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::TrailingData));
+
+    // A value is only read as a list of itself once, not as a list of lists.
+    #[derive(Deserialize, Debug)]
+    struct Tree(#[allow(dead_code)] Vec<Tree>);
+    let err = serde_bencode::from_binary_lenient::<Tree>(b"i1e").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidType { .. }));
+    let err = serde_bencode::from_binary_lenient::<Vec<Vec<u8>>>(b"i1e").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidType { .. }));
+}
+
+#[test]
+fn test_string_decoding() {
+    #[derive(Deserialize, Debug)]
+    struct Name<'a> {
+        #[serde(borrow)]
+        name: Cow<'a, str>,
+    }
+
+    let latin1 = b"d4:name4:caf\xe9e";
+    let utf8 = "d4:name5:café".as_bytes();
+    let utf8 = [utf8, b"e"].concat();
+
+    let err = serde_bencode::from_binary::<Name>(latin1).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidString));
+    assert_eq!(err.path(), "name");
+
+    let mut deserializer = Deserializer::new(latin1).string_decoding(StringDecoding::Lossy);
+    let name = Name::deserialize(&mut deserializer).unwrap().name;
+    assert!(matches!(name, Cow::Owned(name) if name == "caf\u{fffd}"));
+
+    let mut deserializer = Deserializer::new(&utf8).string_decoding(StringDecoding::Lossy);
+    let name = Name::deserialize(&mut deserializer).unwrap().name;
+    assert!(matches!(name, Cow::Borrowed("café")));
+
+    let decoder = StringDecoding::Legacy(|bytes| Some(bytes.iter().map(|&b| b as char).collect()));
+    let mut deserializer = Deserializer::new(b"l4:caf\xe9e").string_decoding(decoder);
+    assert_eq!(
+        Vec::<String>::deserialize(&mut deserializer).unwrap(),
+        ["café"]
+    );
+
+    let mut deserializer = Deserializer::new(b"d4:caf\xe9i1ee").string_decoding(decoder);
+    let map = HashMap::<String, u8>::deserialize(&mut deserializer).unwrap();
+    assert_eq!(map["café"], 1);
+
+    let rejecting = StringDecoding::Legacy(|_| None);
+    let mut deserializer = Deserializer::new(b"4:caf\xe9").string_decoding(rejecting);
+    let err = String::deserialize(&mut deserializer).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidString));
+}
+
+#[test]
+fn test_ignored_values_are_skipped() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct OnlyAnnounce<'a> {
+        announce: &'a str,
+    }
+
+    let torrent =
+        b"d8:announce3:url4:infod5:filesld6:lengthi-1e4:pathl1:aeee6:pieces4:\xff\x00\xfe\x01ee";
+    let value: OnlyAnnounce = serde_bencode::from_binary(torrent).unwrap();
+    assert_eq!(value.announce, "url");
+
+    let (ignored, rest) = serde_bencode::from_binary_prefix::<IgnoredAny>(torrent).unwrap();
+    assert_eq!(ignored, IgnoredAny);
+    assert!(rest.is_empty());
+
+    // Skipped values are still validated.
+    let err = serde_bencode::from_binary::<OnlyAnnounce>(b"d8:announce3:url4:infold1:xi01eeee")
+        .unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::LeadingZero));
+    assert_eq!(err.path(), "info[0].x");
+    assert_eq!(err.offset(), Some(28));
+
+    let err = serde_bencode::from_binary::<IgnoredAny>(b"di1ei2ee").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::KeyMustBeString));
+}
+
+#[test]
+fn test_stream() {
+    let input = b"d2:id1:1e3:abci42ed2:id1:2e";
+    let mut stream = Deserializer::new(input).into_stream::<Value>();
+
+    assert!(matches!(stream.next(), Some(Ok(Value::Dictionary(_)))));
+    assert_eq!(stream.byte_offset(), 9);
+    assert!(matches!(
+        stream.next(),
+        Some(Ok(Value::Bytes(_) | Value::String(_)))
+    ));
+    assert!(matches!(stream.next(), Some(Ok(Value::Number(n))) if n.as_i64() == Some(42)));
+    assert!(matches!(stream.next(), Some(Ok(_))));
+    assert_eq!(stream.byte_offset(), input.len());
+    assert!(stream.next().is_none());
+
+    let values: Vec<i64> = StreamDeserializer::new(b"i1ei2ei3e")
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(values, [1, 2, 3]);
+    assert_eq!(StreamDeserializer::<i64>::new(b"").count(), 0);
+
+    let limits = Limits {
+        max_total_bytes: 4,
+        ..Limits::default()
+    };
+    let values: Vec<String> = Deserializer::new(b"3:abc3:abc")
+        .limits(limits)
+        .into_stream()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(values, ["abc", "abc"]);
+}
+
+#[test]
+fn test_stream_truncated() {
+    let mut stream = StreamDeserializer::<Vec<i64>>::new(b"li1eeli2ei3");
+
+    assert_eq!(stream.next().unwrap().unwrap(), [1]);
+    let err = stream.next().unwrap().unwrap_err();
+    assert!(err.is_eof());
+    assert_eq!(stream.byte_offset(), 5);
+    assert!(stream.next().is_none());
+
+    let mut stream = StreamDeserializer::<Value>::new(b"i1ex");
+    assert!(stream.next().unwrap().is_ok());
+    let err = stream.next().unwrap().unwrap_err();
+    assert!(err.is_syntax());
+    assert_eq!(err.offset(), Some(3));
+}
+
+/// Hands out its input one byte per read, so that every value crosses reads.
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match (self.0.split_first(), buf.first_mut()) {
+            (Some((&b, rest)), Some(out)) => {
+                *out = b;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn test_from_reader() {
+    let files = vec![
+        File {
+            path: vec!["a".to_string(), "b.txt".to_string()],
+            length: 42,
+            checksum: Some("00ff".to_string()),
+            upper: 1,
+        },
+        File {
+            path: vec![],
+            length: 0,
+            checksum: None,
+            upper: 0,
+        },
+    ];
+    let data = serde_bencode::to_binary(&files).unwrap();
+
+    assert_eq!(from_reader::<_, Vec<File>>(&data[..]).unwrap(), files);
+    assert_eq!(from_reader::<_, Vec<File>>(Trickle(&data)).unwrap(), files);
+
+    let messages = vec![
+        Message::Ping,
+        Message::Have(3, "x".to_string()),
+        Message::Request { piece: 1, begin: 2 },
+    ];
+    let data = serde_bencode::to_binary(&messages).unwrap();
+    assert_eq!(
+        from_reader::<_, Vec<Message>>(Trickle(&data)).unwrap(),
+        messages
+    );
+
+    let value: Value = from_reader(Trickle(b"d1:ai-1e1:bl3:\xff\x00\x01ee")).unwrap();
+    assert_eq!(
+        value,
+        serde_bencode::from_binary(b"d1:ai-1e1:bl3:\xff\x00\x01ee").unwrap()
+    );
+}
+
+#[test]
+fn test_from_reader_errors() {
+    let err = from_reader::<_, i64>(Trickle(b"i1ei2e")).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::TrailingData));
+    assert_eq!(err.offset(), Some(3));
+
+    let err = from_reader::<_, Vec<i64>>(Trickle(b"li1e2:abe")).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidType { .. }));
+    assert_eq!(err.offset(), Some(4));
+    assert_eq!(err.path(), "[1]");
+
+    let err = from_reader::<_, IgnoredAny>(Trickle(b"d1:ai1e1:bi01ee")).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::LeadingZero));
+    assert_eq!(err.path(), "b");
+
+    let mut de = Deserializer::from_reader(&b"d1:bi1e1:ai2ee \n"[..]).lenient(true);
+    BTreeMap::<String, i64>::deserialize(&mut de).unwrap();
+    de.end().unwrap();
+    assert_eq!(de.warnings().len(), 2);
+}
+
+#[test]
+fn test_from_reader_huge_length() {
+    // Fails once the input runs out, without allocating for the length first.
+    let err = from_reader::<_, ByteBuf>(&b"4000000000:abc"[..]).unwrap_err();
+
+    assert!(err.is_eof());
+    assert_eq!(err.needed(), Some(4_000_000_000 - 3));
+    assert_eq!(err.offset(), Some(14));
+}
+
+#[test]
+fn test_from_reader_io_error() {
+    struct Broken;
+
+    impl Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+        }
+    }
+
+    let err = from_reader::<_, Value>(Read::chain(&b"li1e"[..], Broken)).unwrap_err();
+
+    assert_eq!(err.classify(), Category::Io);
+    assert!(matches!(err.kind(), ErrorKind::Io(e) if e.kind() == io::ErrorKind::ConnectionReset));
+    assert_eq!(err.offset(), Some(4));
+    assert!(std::error::Error::source(&err).is_some());
+}
+
+#[test]
+fn test_push_parser() {
+    let message = Krpc::Query {
+        t: ByteBuf::from(b"aa".to_vec()),
+        q: "ping".to_string(),
+        a: NodeId {
+            id: ByteBuf::from(b"abcdefghij0123456789".to_vec()),
+        },
+    };
+    let data = serde_bencode::to_binary(&message).unwrap();
+    let mut parser = PushParser::<Krpc>::new();
+
+    for (i, b) in data[..data.len() - 1].iter().enumerate() {
+        let status = parser.feed(std::slice::from_ref(b)).unwrap();
+        assert!(
+            matches!(status, Status::NeedMore { needed } if needed < data.len() - i),
+            "{i}: {status:?}"
+        );
+    }
+    assert_eq!(
+        parser.feed(&data[data.len() - 1..]).unwrap(),
+        Status::Complete {
+            value: message,
+            consumed: data.len(),
+        }
+    );
+    assert_eq!(parser.buffered(), 0);
+}
+
+#[test]
+fn test_push_parser_needed() {
+    let mut parser = PushParser::<Value>::new();
+
+    assert_eq!(parser.feed(b"").unwrap(), Status::NeedMore { needed: 2 });
+    assert_eq!(
+        parser.feed(b"d3:a").unwrap(),
+        Status::NeedMore { needed: 5 }
+    );
+    assert_eq!(parser.feed(b"bc").unwrap(), Status::NeedMore { needed: 3 });
+    assert_eq!(parser.feed(b"li").unwrap(), Status::NeedMore { needed: 4 });
+    assert_eq!(parser.feed(b"-1").unwrap(), Status::NeedMore { needed: 3 });
+    assert_eq!(
+        parser.feed(b"e10:").unwrap(),
+        Status::NeedMore { needed: 12 }
+    );
+    assert!(matches!(
+        parser.feed(b"0123456789ee").unwrap(),
+        Status::Complete { consumed: 26, .. }
+    ));
+}
+
+#[test]
+fn test_push_parser_several_values() {
+    let mut parser = PushParser::<i64>::new();
+
+    assert_eq!(parser.feed(b"i1").unwrap(), Status::NeedMore { needed: 1 });
+    assert_eq!(
+        parser.feed(b"ei2ei3").unwrap(),
+        Status::Complete {
+            value: 1,
+            consumed: 3
+        }
+    );
+    assert_eq!(
+        parser.feed(b"").unwrap(),
+        Status::Complete {
+            value: 2,
+            consumed: 3
+        }
+    );
+    assert_eq!(parser.feed(b"").unwrap(), Status::NeedMore { needed: 1 });
+    assert_eq!(parser.buffered(), 2);
+
+    assert_eq!(
+        parser.feed(b"e3:abci4e").unwrap(),
+        Status::Complete {
+            value: 3,
+            consumed: 3
+        }
+    );
+
+    // A value of the wrong type is dropped, the next one is still read.
+    let err = parser.feed(b"").unwrap_err();
+    assert!(err.is_data());
+    assert_eq!(
+        parser.feed(b"").unwrap(),
+        Status::Complete {
+            value: 4,
+            consumed: 3
+        }
+    );
+}
+
+#[test]
+fn test_push_parser_errors() {
+    let mut parser = PushParser::<Value>::new();
+    parser.feed(b"li1e").unwrap();
+    let err = parser.feed(b"i02ee").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::LeadingZero));
+    assert_eq!(err.offset(), Some(5));
+    assert_eq!(parser.buffered(), 0);
+
+    let err = parser.feed(b"di1ei2ee").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::KeyMustBeString));
+    assert_eq!(err.offset(), Some(1));
+
+    let err = parser.feed(b"l1:ax").unwrap_err();
+    assert!(err.is_syntax());
+    assert_eq!(err.offset(), Some(4));
+
+    // Broken input is reported as such even if it doesn't fit `T` either.
+    let mut parser = PushParser::<i64>::new();
+    let err = parser.feed(b"l1:ax").unwrap_err();
+    assert!(err.is_syntax());
+    assert_eq!(err.offset(), Some(4));
+    assert_eq!(parser.buffered(), 0);
+
+    // Never-ending input is cut short by the limits instead of buffered.
+    let mut parser = PushParser::<Value>::new().limits(Limits {
+        max_bytes_len: 10,
+        ..Limits::default()
+    });
+    let err = parser.feed(b"4000000000:").unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::LimitExceeded(Limit::BytesLen)
+    ));
+    let err = parser.feed(&[b'l'; 200]).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::LimitExceeded(Limit::Depth)));
+
+    let mut parser = PushParser::<Value>::new().limits(Limits {
+        max_depth: 10,
+        max_bytes_len: 10,
+        max_collection_len: 10,
+        max_total_bytes: 10,
+        max_integer_len: 10,
+    });
+    let digits = [&b"i"[..], &[b'1'; 1_000_000]].concat();
+    let err = parser.feed(&digits).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::LimitExceeded(Limit::IntegerLen)
+    ));
+    assert_eq!(parser.buffered(), 0);
+    assert!(matches!(
+        parser.feed(b"i12345").unwrap(),
+        Status::NeedMore { .. }
+    ));
+    let err = parser.feed(b"678901").unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::LimitExceeded(Limit::IntegerLen)
+    ));
+    assert_eq!(err.offset(), Some(1));
+    let err = parser.feed(&[b'0'; 11]).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        ErrorKind::LimitExceeded(Limit::IntegerLen)
+    ));
+}
+
+#[test]
+fn test_iter_list() {
+    let mut de = Deserializer::new(b"li1ei2ei3ee");
+    let mut sum = 0;
+    for n in de.iter_list::<i64>().unwrap() {
+        sum += n.unwrap();
+    }
+    assert_eq!(sum, 6);
+    de.end().unwrap();
+
+    let mut de = Deserializer::new(b"li1e1:ai3ee");
+    let mut iter = de.iter_list::<i64>().unwrap();
+    assert_eq!(iter.next().unwrap().unwrap(), 1);
+    let err = iter.next().unwrap().unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidType { .. }));
+    assert_eq!(err.path(), "[1]");
+    assert_eq!(err.offset(), Some(4));
+    assert!(iter.next().is_none());
+
+    let err = Deserializer::new(b"de").iter_list::<i64>().err().unwrap();
+    assert!(matches!(err.kind(), ErrorKind::InvalidType { .. }));
+
+    // The nesting level is left when the iterator is dropped, even early.
+    let limits = Limits {
+        max_depth: 1,
+        ..Limits::default()
+    };
+    let mut de = Deserializer::new(b"lli1eee").limits(limits);
+    drop(de.iter_list::<Value>().unwrap());
+    let mut iter = de.iter_list::<i64>().unwrap();
+    assert_eq!(iter.next().unwrap().unwrap(), 1);
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn test_iter_dict() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Resume {
+        downloaded: u64,
+    }
+
+    let data = b"d9:a.torrentd10:downloadedi10ee9:b.torrentd10:downloadedi20e4:seedi1eee";
+    let mut de = Deserializer::from_reader(Trickle(data));
+    let entries = de
+        .iter_dict::<String, Resume>()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        entries,
+        [
+            ("a.torrent".to_string(), Resume { downloaded: 10 }),
+            ("b.torrent".to_string(), Resume { downloaded: 20 }),
+        ]
+    );
+    de.end().unwrap();
+
+    let mut de = Deserializer::new(b"d1:bi1e1:ai2ee").canonical(true);
+    let mut iter = de.iter_dict::<&str, i64>().unwrap();
+    assert_eq!(iter.next().unwrap().unwrap(), ("b", 1));
+    let err = iter.next().unwrap().unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnsortedKey(_)));
+    assert!(iter.next().is_none());
+
+    let mut de = Deserializer::new(b"d1:ad1:bi1eee").limits(Limits {
+        max_depth: 1,
+        ..Limits::default()
+    });
+    let err = de
+        .iter_dict::<&str, Value>()
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::LimitExceeded(Limit::Depth)));
+    assert_eq!(err.path(), "a");
+
+    let mut de = Deserializer::new(b"dd1:ai1eee").limits(Limits {
+        max_depth: 1,
+        ..Limits::default()
+    });
+    drop(de.iter_dict::<&str, Value>().unwrap());
+    let mut iter = de.iter_dict::<&str, i64>().unwrap();
+    assert_eq!(iter.next().unwrap().unwrap(), ("a", 1));
+    assert!(iter.next().is_none());
+}